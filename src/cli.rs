@@ -0,0 +1,592 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::level_filters::LevelFilter;
+use risemode_driver::filter;
+use risemode_driver::protocol::Frame;
+use risemode_driver::{DeviceSelector, GpuTemperature, HwmonAggregate, PRODUCT_ID, VENDOR_ID};
+
+/// Sends the CPU temperature to the Rise Mode Aura Ice Black water cooler display.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub options: Options,
+    
+    /// What to do; defaults to `run`
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Continuously send the CPU temperature to the display (default)
+    Run,
+    /// List connected displays matching the vendor/product ID
+    ListDevices,
+    /// List the saved display nicknames, or name a display persistently
+    Nickname {
+        /// Nickname to set or remove, e.g. TopRad
+        name: Option<String>,
+        
+        /// Display it names: serial:<SERIAL>, usb:<PORT> or /dev/hidrawN
+        #[arg(requires = "name", conflicts_with = "remove")]
+        selector: Option<DeviceSelector>,
+        
+        /// Remove the nickname instead
+        #[arg(long, requires = "name")]
+        remove: bool,
+    },
+    /// Send a single temperature to the display and exit
+    Send {
+        /// Value to show, sent as is (in --unit). Either `.` or `,` works as
+        /// the decimal separator
+        #[arg(long, value_parser = parse_decimal, allow_hyphen_values = true)]
+        temp: f32,
+    },
+    /// Send arbitrary bytes to the display once, for protocol experiments
+    SendRaw {
+        /// Payload in hex, e.g. `3a 01 00` or `3a0100`; zero-padded to a full frame
+        #[arg(required = true, value_name = "HEX")]
+        bytes: Vec<String>,
+        
+        /// Send exactly the given bytes instead of padding to 24
+        #[arg(long)]
+        no_pad: bool,
+    },
+    /// Blink one display for a few seconds to see which physical unit it is
+    Identify {
+        /// A name from --device or `nickname`, or a selector (serial:, usb: or /dev/hidrawN)
+        display: String,
+        
+        /// How long to blink, in seconds
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..=60))]
+        seconds: u64,
+    },
+    /// Check that the display and the temperature sensors are usable
+    Probe,
+    /// List hwmon temperatures with the labels from the lm-sensors configuration
+    Sensors,
+    /// Play a file of timed display commands (show <VALUE>, wait <DURATION>, blank)
+    Script {
+        /// The script to play
+        file: PathBuf,
+    },
+    /// Disconnect and reconnect the display over USB to recover a hung controller (needs root)
+    PowerCycle,
+    /// Show local usage counters (uptime, frames sent, reconnects, sensor failures)
+    Info,
+    /// Run a scripted hardware test against the connected display and write a JUnit report
+    HilTest {
+        /// Where to write the JUnit XML report
+        #[arg(long, default_value = "risemode-hil.xml")]
+        junit: PathBuf,
+    },
+    /// Drive the display at a high frame rate for hours, logging write errors and disconnects
+    Soak {
+        /// Frames per second, e.g. 20 or 20hz
+        #[arg(long, default_value = "20hz", value_parser = parse_rate)]
+        rate: f64,
+        
+        /// How long to run
+        #[arg(long, default_value_t = 1.0, value_parser = parse_hours)]
+        hours: f64,
+    },
+    /// Write the udev rule and systemd service for the display (Linux, needs root)
+    Install {
+        /// Install below this directory instead of /, e.g. when packaging
+        #[arg(long, default_value = "/")]
+        root: PathBuf,
+        
+        /// Also enable and start the service
+        #[arg(long)]
+        enable: bool,
+        
+        /// Replace existing files
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Settings shared by all subcommands.
+#[derive(Debug, Args)]
+pub struct Options {
+    /// Time between updates, in milliseconds
+    #[arg(long, global = true, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+    
+    /// Least severe log messages to show; the reading sent every tick is logged at debug
+    #[arg(long, global = true, value_enum, default_value_t = LogLevel::Info)]
+    pub log_level: LogLevel,
+    
+    /// Where and how to log: journald under systemd, text to stderr otherwise
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Auto)]
+    pub log_format: LogFormat,
+    
+    /// USB vendor ID of the display, in hex [default: aa88]
+    #[arg(long, global = true, value_parser = parse_hex_id)]
+    vid: Option<u16>,
+    
+    /// USB product ID of the display, in hex [default: 8666]
+    #[arg(long, global = true, value_parser = parse_hex_id)]
+    pid: Option<u16>,
+    
+    /// Only use this display, optionally naming it for log messages: a
+    /// selector (serial:<SERIAL>, usb:<PORT> or /dev/hidrawN, see
+    /// list-devices), or NAME=SELECTOR such as front=usb:1-4. Repeat for
+    /// several displays. By default every matching display is used
+    #[arg(long = "device", global = true, value_name = "[NAME=]SELECTOR", value_parser = parse_device_spec)]
+    pub devices: Vec<DeviceSpec>,
+    
+    /// What a display named with --device shows instead of --metric, as
+    /// NAME=METRIC[,METRIC...] such as top=gpu-temperature. Repeatable
+    #[arg(long, global = true, value_name = "NAME=METRICS", value_parser = parse_device_metric)]
+    pub device_metric: Vec<DeviceMetric>,
+    
+    /// What to show on the display. Give several, separated by commas, to
+    /// rotate between them
+    #[arg(long, global = true, value_enum, value_delimiter = ',', default_value = "temperature")]
+    pub metric: Vec<Metric>,
+    
+    /// Unit temperatures are shown in
+    #[arg(long, global = true, value_enum, default_value_t = TemperatureUnit::Celsius)]
+    pub unit: TemperatureUnit,
+    
+    /// How long each metric stays on the display when rotating, in seconds
+    #[arg(long, global = true, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    pub rotate_every: u64,
+    
+    /// Where to read the temperature from
+    #[arg(long, global = true, value_enum, default_value_t = SensorKind::Auto)]
+    pub sensor: SensorKind,
+    
+    /// Which AMD GPU temperature to use with --sensor amdgpu or hottest
+    #[arg(long, global = true, value_enum, default_value_t = GpuSensor::Edge)]
+    pub gpu_temp: GpuSensor,
+    
+    /// Only read the hwmon chip belonging to this device when several match
+    /// (e.g. 0000:00:18.3 or coretemp.1, see `readlink /sys/class/hwmon/hwmon*/device`).
+    /// By default the hottest of all CPU chips is shown
+    #[arg(long, global = true)]
+    pub hwmon_device: Option<String>,
+    
+    /// Only read hwmon chips with exactly this name (e.g. k10temp, see
+    /// `cat /sys/class/hwmon/hwmon*/name`) instead of guessing from the name
+    #[arg(long, global = true)]
+    pub hwmon_name: Option<String>,
+    
+    /// Read this input of the hwmon chip instead of temp1_input (e.g. temp3_input)
+    #[arg(long, global = true, conflicts_with = "hwmon_label")]
+    pub hwmon_input: Option<String>,
+    
+    /// Read the hwmon input with this label instead of temp1_input (e.g. Tdie,
+    /// see `cat /sys/class/hwmon/hwmon*/temp*_label`)
+    #[arg(long, global = true)]
+    pub hwmon_label: Option<String>,
+    
+    /// Combine all temp*_input files of the hwmon chip instead of reading one
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = Aggregate::Single,
+        conflicts_with_all = ["hwmon_input", "hwmon_label"]
+    )]
+    pub hwmon_aggregate: Aggregate,
+    
+    /// With run: read the sensors and print the frames as hex instead of
+    /// sending them, without opening any display
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    
+    /// Leave USB autosuspend for the display at the kernel default
+    #[arg(long, global = true)]
+    pub keep_autosuspend: bool,
+    
+    /// Restrict the running driver to the files it needs with Landlock (Linux 5.13+)
+    #[arg(long, global = true)]
+    pub sandbox: bool,
+    
+    /// What the display shows when the driver stops (SIGTERM/SIGINT) or a
+    /// sensor stops responding: hold (the last value), blank, a number such
+    /// as 0, or pattern (blinking 88.8)
+    #[arg(long, global = true, default_value = "hold", value_parser = parse_parting_frame)]
+    pub parting_frame: PartingFrame,
+    
+    /// Temperatures below this (°C) are treated as sensor glitches and ignored
+    #[arg(
+        long,
+        global = true,
+        default_value_t = filter::DEFAULT_MIN_TEMP,
+        value_parser = parse_bound,
+        allow_hyphen_values = true
+    )]
+    pub min_temp: f32,
+    
+    /// Temperatures above this (°C) are treated as sensor glitches and ignored
+    #[arg(long, global = true, default_value_t = filter::DEFAULT_MAX_TEMP, value_parser = parse_bound)]
+    pub max_temp: f32,
+    
+    /// Largest believable change between two readings (°C); bigger jumps
+    /// are ignored unless they persist
+    #[arg(long, global = true, default_value_t = filter::DEFAULT_MAX_JUMP, value_parser = parse_bound)]
+    pub max_jump: f32,
+    
+    /// Disable the median-of-3 filter that hides one-off sensor spikes
+    #[arg(long, global = true)]
+    pub no_spike_filter: bool,
+    
+    /// Smooth readings before they are shown, to stop the display flickering
+    /// between neighbouring values
+    #[arg(long, global = true, value_enum, default_value_t = Smoothing::None)]
+    pub smoothing: Smoothing,
+    
+    /// Number of readings --smoothing averages over
+    #[arg(long, global = true, default_value_t = 5, value_parser = clap::value_parser!(u64).range(2..=600))]
+    pub smoothing_window: u64,
+    
+    /// Blank the display while the temperature stays below this value (°C),
+    /// e.g. to keep a bedroom dark while the machine idles
+    #[arg(long, global = true)]
+    pub idle_blank_below: Option<f32>,
+    
+    /// How long the temperature must stay below --idle-blank-below before
+    /// blanking, in seconds
+    #[arg(long, global = true, default_value_t = 300, requires = "idle_blank_below")]
+    pub idle_blank_after: u64,
+    
+    /// Ease the displayed value up from 0 over this many seconds after
+    /// connecting, instead of jumping straight to it. 0 turns it off
+    #[arg(long, global = true, default_value_t = 0, value_parser = clap::value_parser!(u64).range(..=300))]
+    pub ramp_up: u64,
+    
+    /// Publish the readings and the driver status to this MQTT broker, as
+    /// HOST or HOST:PORT (default port 1883, IPv6 as [ADDRESS]:PORT), with
+    /// Home Assistant discovery. Plain TCP, QoS 0
+    #[arg(long, global = true, value_name = "HOST[:PORT]")]
+    pub mqtt: Option<String>,
+    
+    /// User name for --mqtt; the password is read from $RISEMODE_MQTT_PASSWORD
+    #[arg(long, global = true, requires = "mqtt")]
+    pub mqtt_username: Option<String>,
+    
+    /// Topics are published under PREFIX/<hostname>/
+    #[arg(long, global = true, value_name = "PREFIX", default_value = "risemode")]
+    pub mqtt_topic_prefix: String,
+    
+    /// Prefix Home Assistant reads discovery topics from
+    #[arg(long, global = true, value_name = "PREFIX", default_value = "homeassistant")]
+    pub mqtt_discovery_prefix: String,
+    
+    /// Write the readings and counters to this file every tick, in
+    /// Prometheus text format, e.g. for node_exporter's textfile collector
+    /// (/var/lib/node_exporter/textfile_collector/risemode.prom)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub textfile: Option<PathBuf>,
+    
+    /// Offer the org.risemode.Driver1 control interface on this D-Bus bus
+    /// (Linux), to pause updates or switch the metric at runtime
+    #[arg(long, global = true, value_enum)]
+    pub dbus: Option<DbusBus>,
+    
+    /// Names saved with `nickname`, filled in after parsing
+    #[arg(skip)]
+    pub nicknames: Vec<DeviceSpec>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Metric {
+    /// Temperature from --sensor, in °C
+    Temperature,
+    /// AMD GPU temperature (--gpu-temp), in °C
+    GpuTemperature,
+    /// Overall CPU load from /proc/stat, in percent
+    Load,
+    /// RAM in use from /proc/meminfo, in percent
+    Memory,
+}
+
+impl Metric {
+    /// The name used on the command line, e.g. `gpu-temperature`.
+    pub fn name(self) -> String {
+        self.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// Converts a reading in °C to this unit.
+    pub fn convert(self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+    
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// journald when started by systemd, text otherwise
+    Auto,
+    /// Human-readable lines on stderr
+    Text,
+    /// One JSON object per line on stderr
+    Json,
+    /// Structured entries in the systemd journal (Linux)
+    Journald,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DbusBus {
+    /// The desktop user's bus, for running the driver as that user
+    Session,
+    /// The system bus, for the service; needs the packaged bus policy
+    System,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SensorKind {
+    /// hwmon, falling back to thermal zones
+    Auto,
+    /// /sys/class/hwmon only
+    Hwmon,
+    /// /sys/class/thermal only
+    Thermal,
+    /// AMD GPU via the amdgpu hwmon driver
+    Amdgpu,
+    /// Hottest of the CPU and the AMD GPU
+    Hottest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Smoothing {
+    /// Show every reading as is
+    None,
+    /// Exponential moving average
+    Ema,
+    /// Average of the last --smoothing-window readings
+    Average,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Aggregate {
+    /// temp1_input, or the input chosen with --hwmon-input/--hwmon-label
+    Single,
+    /// Hottest input
+    Max,
+    /// Average of the inputs
+    Average,
+}
+
+impl From<Aggregate> for HwmonAggregate {
+    fn from(aggregate: Aggregate) -> Self {
+        match aggregate {
+            Aggregate::Single => HwmonAggregate::Single,
+            Aggregate::Max => HwmonAggregate::Max,
+            Aggregate::Average => HwmonAggregate::Average,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GpuSensor {
+    /// Edge of the die
+    Edge,
+    /// Hotspot of the die (Vega and newer)
+    Junction,
+    /// VRAM/HBM, where exposed
+    Memory,
+}
+
+impl From<GpuSensor> for GpuTemperature {
+    fn from(sensor: GpuSensor) -> Self {
+        match sensor {
+            GpuSensor::Edge => GpuTemperature::Edge,
+            GpuSensor::Junction => GpuTemperature::Junction,
+            GpuSensor::Memory => GpuTemperature::Memory,
+        }
+    }
+}
+
+/// What is left on the display once no more readings will be sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartingFrame {
+    /// Send nothing, so the last value stays
+    Hold,
+    Blank,
+    /// A fixed value, sent as is
+    Value(f32),
+    /// 88.8 blinking (in steady state once the driver has exited)
+    Pattern,
+}
+
+impl PartingFrame {
+    /// The frame to send, `lit` alternating on every tick for the blinking
+    /// pattern. `None` for [`PartingFrame::Hold`].
+    pub fn frame(self, lit: bool) -> Option<Frame> {
+        match self {
+            PartingFrame::Hold => None,
+            PartingFrame::Blank => Some(Frame::new()),
+            // Checked when parsing the option
+            PartingFrame::Value(value) => Frame::temperature(value).ok(),
+            PartingFrame::Pattern if lit => Frame::temperature(88.8).ok(),
+            PartingFrame::Pattern => Some(Frame::new()),
+        }
+    }
+}
+
+/// A display selected with `--device`.
+#[derive(Debug, Clone)]
+pub struct DeviceSpec {
+    pub name: Option<String>,
+    pub selector: DeviceSelector,
+}
+
+/// Metrics for one named display, from `--device-metric`.
+#[derive(Debug, Clone)]
+pub struct DeviceMetric {
+    pub name: String,
+    pub metrics: Vec<Metric>,
+}
+
+impl Options {
+    pub fn vendor_id(&self) -> u16 {
+        self.vid.unwrap_or(VENDOR_ID)
+    }
+    
+    pub fn product_id(&self) -> u16 {
+        self.pid.unwrap_or(PRODUCT_ID)
+    }
+}
+
+fn parse_hex_id(value: &str) -> Result<u16, String> {
+    let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|e| format!("expected a 16-bit hex ID such as aa88: {}", e))
+}
+
+fn parse_device_spec(value: &str) -> Result<DeviceSpec, String> {
+    // Selectors never contain '=', so anything before it is a name
+    match value.split_once('=') {
+        Some((name, selector)) if !name.is_empty() => {
+            Ok(DeviceSpec { name: Some(name.to_string()), selector: selector.parse()? })
+        }
+        Some(_) => Err("the name before '=' is empty".to_string()),
+        None => Ok(DeviceSpec { name: None, selector: value.parse()? }),
+    }
+}
+
+fn parse_device_metric(value: &str) -> Result<DeviceMetric, String> {
+    let (name, metrics) = value.split_once('=').ok_or("expected NAME=METRIC[,METRIC...]")?;
+    if name.is_empty() {
+        return Err("the name before '=' is empty".to_string());
+    }
+    
+    let metrics = metrics
+        .split(',')
+        .map(|metric| Metric::from_str(metric.trim(), true))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(DeviceMetric { name: name.to_string(), metrics })
+}
+
+/// Parses a number typed with either a decimal point or a decimal comma
+/// (`42.5` or `42,5`).
+fn parse_decimal(value: &str) -> Result<f32, String> {
+    value
+        .trim()
+        .replacen(',', ".", 1)
+        .parse()
+        .map_err(|e| format!("expected a number such as 42.5 or 42,5: {}", e))
+}
+
+/// Parses a bound for the sanity checks; NaN would turn them off.
+fn parse_bound(value: &str) -> Result<f32, String> {
+    match parse_decimal(value)? {
+        bound if bound.is_finite() => Ok(bound),
+        _ => Err("expected a finite number".to_string()),
+    }
+}
+
+fn parse_parting_frame(value: &str) -> Result<PartingFrame, String> {
+    match value.trim() {
+        "hold" => Ok(PartingFrame::Hold),
+        "blank" => Ok(PartingFrame::Blank),
+        "pattern" => Ok(PartingFrame::Pattern),
+        number => match parse_decimal(number) {
+            Ok(value) if Frame::temperature(value).is_ok() => Ok(PartingFrame::Value(value)),
+            _ => Err("expected hold, blank, pattern or a number between 0 and 255.9".to_string()),
+        },
+    }
+}
+
+/// Parses a frame rate such as `20` or `20hz`.
+fn parse_rate(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+    let number = value.strip_suffix("hz").or_else(|| value.strip_suffix("Hz")).unwrap_or(value);
+    match number.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate <= 1000.0 => Ok(rate),
+        _ => Err("expected a rate between 0 and 1000 Hz, such as 20hz".to_string()),
+    }
+}
+
+fn parse_hours(value: &str) -> Result<f64, String> {
+    match value.trim().parse::<f64>() {
+        Ok(hours) if hours > 0.0 && Duration::try_from_secs_f64(hours * 3600.0).is_ok() => Ok(hours),
+        _ => Err("expected a positive number of hours such as 8 or 0.5".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn bounds_must_be_finite() {
+        assert_eq!(parse_bound("42,5"), Ok(42.5));
+        assert_eq!(parse_bound("-10"), Ok(-10.0));
+        for value in ["nan", "NaN", "inf", "-inf", "1e40", "warm"] {
+            assert!(parse_bound(value).is_err(), "{}", value);
+        }
+    }
+    
+    #[test]
+    fn soak_hours_must_fit_a_duration() {
+        assert_eq!(parse_hours("8"), Ok(8.0));
+        assert_eq!(parse_hours(" 0.5 "), Ok(0.5));
+        for value in ["0", "-1", "nan", "inf", "1e300", "long"] {
+            assert!(parse_hours(value).is_err(), "{}", value);
+        }
+    }
+}
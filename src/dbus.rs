@@ -0,0 +1,163 @@
+//! Runtime control over D-Bus (`--dbus`): the `org.risemode.Driver1`
+//! interface at `/org/risemode/Driver1`.
+//!
+//! zbus answers calls on its own thread. Commands only change shared state,
+//! which the send loop picks up on its next tick, so a call never touches the
+//! display directly.
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+
+use crate::cli::{DbusBus, Metric};
+
+#[cfg(target_os = "linux")]
+pub const BUS_NAME: &str = "org.risemode.Driver1";
+#[cfg(target_os = "linux")]
+pub const OBJECT_PATH: &str = "/org/risemode/Driver1";
+
+/// What the interface and the send loop share.
+#[derive(Debug, Default)]
+struct Shared {
+    paused: bool,
+    /// What the displays without a `--device-metric` show
+    metrics: Vec<Metric>,
+    metrics_changed: bool,
+    /// Short machine-readable state, as published over MQTT
+    state: String,
+    /// The same text as `systemctl status`
+    status: String,
+}
+
+#[cfg(target_os = "linux")]
+pub struct DbusService {
+    _connection: zbus::blocking::Connection,
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[cfg(target_os = "linux")]
+impl DbusService {
+    /// Claims [`BUS_NAME`] on `bus` and starts answering calls. `metrics` is
+    /// what `Metric` reports until `SetMetric` is called.
+    pub fn start(bus: DbusBus, metrics: &[Metric]) -> Result<Self> {
+        use anyhow::Context;
+        use zbus::blocking::connection::Builder;
+        
+        let shared = Arc::new(Mutex::new(Shared { metrics: metrics.to_vec(), ..Shared::default() }));
+        let builder = match bus {
+            DbusBus::Session => Builder::session(),
+            DbusBus::System => Builder::system(),
+        };
+        let connection = builder
+            .and_then(|builder| builder.name(BUS_NAME))
+            .and_then(|builder| builder.serve_at(OBJECT_PATH, Driver { shared: shared.clone() }))
+            .and_then(|builder| builder.build())
+            .with_context(|| format!("Failed to offer {} on the {:?} bus", BUS_NAME, bus))?;
+        
+        tracing::info!("Listening for D-Bus calls on {} ({:?} bus)", BUS_NAME, bus);
+        Ok(Self { _connection: connection, shared })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub struct DbusService {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl DbusService {
+    pub fn start(_bus: DbusBus, _metrics: &[Metric]) -> Result<Self> {
+        anyhow::bail!("--dbus is only supported on Linux")
+    }
+}
+
+impl DbusService {
+    /// Set by the `Pause` method until `Resume`.
+    pub fn is_paused(&self) -> bool {
+        self.shared.lock().unwrap().paused
+    }
+    
+    /// The metrics chosen with `SetMetric`, once per call.
+    pub fn take_metric_change(&self) -> Option<Vec<Metric>> {
+        let mut shared = self.shared.lock().unwrap();
+        if !shared.metrics_changed {
+            return None;
+        }
+        
+        shared.metrics_changed = false;
+        Some(shared.metrics.clone())
+    }
+    
+    /// What the default displays should show, `--metric` unless changed.
+    pub fn metrics(&self) -> Vec<Metric> {
+        self.shared.lock().unwrap().metrics.clone()
+    }
+    
+    pub fn set_state(&self, state: &str) {
+        self.shared.lock().unwrap().state = state.to_string();
+    }
+    
+    pub fn set_status(&self, status: &str) {
+        self.shared.lock().unwrap().status = status.to_string();
+    }
+}
+
+/// The object served at [`OBJECT_PATH`].
+#[cfg(target_os = "linux")]
+struct Driver {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[cfg(target_os = "linux")]
+#[zbus::interface(name = "org.risemode.Driver1")]
+impl Driver {
+    /// Stops sending frames; the display keeps showing the last one.
+    fn pause(&self) {
+        self.shared.lock().unwrap().paused = true;
+    }
+    
+    fn resume(&self) {
+        self.shared.lock().unwrap().paused = false;
+    }
+    
+    /// Switches the displays without a `--device-metric` to these metrics,
+    /// comma-separated names as for `--metric` (e.g. `temperature,load`).
+    fn set_metric(&self, metrics: &str) -> zbus::fdo::Result<()> {
+        use clap::ValueEnum;
+        
+        let parsed = metrics
+            .split(',')
+            .map(|name| {
+                Metric::from_str(name.trim(), true)
+                    .map_err(|_| zbus::fdo::Error::InvalidArgs(format!("Unknown metric {:?}", name.trim())))
+            })
+            .collect::<zbus::fdo::Result<Vec<_>>>()?;
+        
+        let mut shared = self.shared.lock().unwrap();
+        shared.metrics = parsed;
+        shared.metrics_changed = true;
+        Ok(())
+    }
+    
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn paused(&self) -> bool {
+        self.shared.lock().unwrap().paused
+    }
+    
+    /// The current metrics, comma-separated.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn metric(&self) -> String {
+        self.shared.lock().unwrap().metrics.iter().map(|metric| metric.name()).collect::<Vec<_>>().join(",")
+    }
+    
+    /// `connecting`, `waiting_for_sensor`, `streaming`, `paused`, `reconnecting` or `stopped`.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn state(&self) -> String {
+        self.shared.lock().unwrap().state.clone()
+    }
+    
+    /// The last readings or problem, as shown by `systemctl status`.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn status(&self) -> String {
+        self.shared.lock().unwrap().status.clone()
+    }
+}
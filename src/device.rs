@@ -0,0 +1,332 @@
+//! Discovery and access to the display over USB HID.
+
+use anyhow::{Context, Result};
+use hidapi::{DeviceInfo, HidApi};
+use std::fmt;
+use std::fs::File;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::protocol::{Frame, InputReport};
+use crate::transport::HidTransport;
+use crate::usb;
+
+// Vendor ID and Product ID for the water cooler display
+pub const VENDOR_ID: u16 = 0xaa88; // 43656 in decimal
+pub const PRODUCT_ID: u16 = 0x8666; // 34406 in decimal
+
+/// Largest report read back from the device; full-speed HID reports are at
+/// most 64 bytes.
+const MAX_INPUT_REPORT_LEN: usize = 64;
+
+/// Returned when another driver instance already holds a device's lock.
+#[derive(Debug)]
+pub struct DeviceInUse {
+    pub path: String,
+}
+
+impl fmt::Display for DeviceInUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is already in use by another risemode-driver instance", self.path)
+    }
+}
+
+impl std::error::Error for DeviceInUse {}
+
+/// An opened display together with the advisory lock that marks it as ours.
+pub struct RisemodeDevice {
+    device: Box<dyn HidTransport + Send>,
+    path: String,
+    name: Option<String>,
+    _lock: Option<File>,
+}
+
+impl RisemodeDevice {
+    /// Locks and opens the display described by `info`.
+    ///
+    /// Fails with [`DeviceInUse`] if another process already holds the lock.
+    pub fn open(api: &HidApi, info: &DeviceInfo) -> Result<Self> {
+        let path = info.path().to_string_lossy().into_owned();
+        let lock = lock_device(&path)?;
+        let device = info
+            .open_device(api)
+            .with_context(|| format!("Failed to open {}", path))?;
+        
+        Ok(Self { device: Box::new(device), path, name: None, _lock: lock })
+    }
+    
+    /// A display reached through `transport` instead of hidapi, e.g. a
+    /// `testing::MockTransport` in tests. `path` only names it; nothing is
+    /// opened or locked.
+    pub fn with_transport(transport: impl HidTransport + Send + 'static, path: impl Into<String>) -> Self {
+        Self { device: Box::new(transport), path: path.into(), name: None, _lock: None }
+    }
+    
+    /// Opens every connected display. Devices that fail to open are reported
+    /// and skipped; a device held by another instance aborts the whole scan.
+    pub fn open_all(api: &HidApi) -> Result<Vec<Self>> {
+        Self::open_matching(api, VENDOR_ID, PRODUCT_ID)
+    }
+    
+    /// Like [`RisemodeDevice::open_all`], for displays that enumerate with a
+    /// different vendor/product ID.
+    pub fn open_matching(api: &HidApi, vendor_id: u16, product_id: u16) -> Result<Vec<Self>> {
+        let mut devices = Vec::new();
+        
+        for device_info in find_displays(api, vendor_id, product_id) {
+            debug!("Found device: {:?}", device_info.path());
+            match Self::open(api, device_info) {
+                Ok(device) => {
+                    debug!("Successfully opened HID device");
+                    devices.push(device);
+                }
+                Err(e) if e.is::<DeviceInUse>() => return Err(e),
+                Err(e) => {
+                    warn!("Failed to open device: {:#}", e);
+                }
+            }
+        }
+        
+        Ok(devices)
+    }
+    
+    /// hidraw node the device was opened from, e.g. `/dev/hidraw3`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    
+    /// Name for log messages: the one given with [`RisemodeDevice::set_name`],
+    /// or the path.
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.path)
+    }
+    
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = Some(name.into());
+    }
+    
+    /// Sends a frame to the display.
+    pub fn send(&self, frame: &Frame) -> Result<()> {
+        self.write_report(&frame.to_bytes())
+    }
+    
+    /// Sends a report of any length, for experimenting with the protocol.
+    /// Normal frames go through [`RisemodeDevice::send`].
+    pub fn write_report(&self, report: &[u8]) -> Result<()> {
+        self.device
+            .write(report)
+            .with_context(|| format!("HID write to {} failed", self.name()))?;
+        Ok(())
+    }
+    
+    /// Waits up to `timeout` for a report from the device; `Duration::ZERO`
+    /// only checks for one that has already arrived.
+    pub fn read_report(&self, timeout: Duration) -> Result<Option<InputReport>> {
+        let mut buf = [0u8; MAX_INPUT_REPORT_LEN];
+        let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+        let len = self
+            .device
+            .read_timeout(&mut buf, timeout_ms)
+            .with_context(|| format!("HID read from {} failed", self.name()))?;
+        
+        Ok((len > 0).then(|| InputReport::new(&buf[..len])))
+    }
+}
+
+/// Lists the HID devices with the given vendor/product ID, normally
+/// [`VENDOR_ID`] and [`PRODUCT_ID`].
+pub fn find_devices(api: &HidApi, vendor_id: u16, product_id: u16) -> impl Iterator<Item = &DeviceInfo> {
+    api.device_list()
+        .filter(move |info| info.vendor_id() == vendor_id && info.product_id() == product_id)
+}
+
+/// Picks out one display when several identical ones are connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// USB serial number, `serial:<SERIAL>`
+    Serial(String),
+    /// USB port as named in sysfs, `usb:1-4` (or `usb:1-4.2` behind a hub).
+    /// Stays the same as long as the cable does
+    UsbPort(String),
+    /// hidraw node such as `/dev/hidraw3`, which can change across boots
+    Path(String),
+}
+
+impl DeviceSelector {
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        match self {
+            DeviceSelector::Serial(serial) => info.serial_number() == Some(serial.as_str()),
+            DeviceSelector::UsbPort(port) => usb_port(info).as_deref() == Some(port.as_str()),
+            DeviceSelector::Path(path) => info.path().to_string_lossy() == path.as_str(),
+        }
+    }
+}
+
+impl FromStr for DeviceSelector {
+    type Err = String;
+    
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(serial) = value.strip_prefix("serial:") {
+            Ok(DeviceSelector::Serial(serial.to_string()))
+        } else if let Some(port) = value.strip_prefix("usb:") {
+            Ok(DeviceSelector::UsbPort(port.to_string()))
+        } else if value.starts_with('/') {
+            Ok(DeviceSelector::Path(value.to_string()))
+        } else {
+            Err(format!("expected serial:<SERIAL>, usb:<PORT> or a /dev/hidraw path, got {:?}", value))
+        }
+    }
+}
+
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSelector::Serial(serial) => write!(f, "serial:{}", serial),
+            DeviceSelector::UsbPort(port) => write!(f, "usb:{}", port),
+            DeviceSelector::Path(path) => f.write_str(path),
+        }
+    }
+}
+
+/// The sysfs name of the USB port a display is plugged into, e.g. `1-4`.
+pub fn usb_port(info: &DeviceInfo) -> Option<String> {
+    let usb_dir = usb::sysfs_device(&info.path().to_string_lossy())?;
+    Some(usb_dir.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Like [`find_devices`], but with one entry per physical display.
+///
+/// A display can enumerate several times: once per HID interface, and on
+/// Linux once per top-level collection of the same hidraw node. Writing to
+/// every entry would send each frame twice, so entries are grouped by the
+/// USB device they belong to (falling back to the serial number where sysfs
+/// isn't available) and the vendor-defined interface is kept.
+pub fn find_displays(api: &HidApi, vendor_id: u16, product_id: u16) -> Vec<&DeviceInfo> {
+    let infos: Vec<&DeviceInfo> = find_devices(api, vendor_id, product_id).collect();
+    let interfaces: Vec<Interface> = infos
+        .iter()
+        .map(|info| {
+            let path = info.path().to_string_lossy().into_owned();
+            let serial = info.serial_number().filter(|serial| !serial.is_empty());
+            let physical = usb::sysfs_device(&path)
+                .map(|dir| dir.display().to_string())
+                .or_else(|| serial.map(|serial| format!("serial:{}", serial)))
+                .unwrap_or(path);
+            
+            Interface { physical, number: info.interface_number(), usage_page: info.usage_page() }
+        })
+        .collect();
+    
+    select_unique(&interfaces).into_iter().map(|index| infos[index]).collect()
+}
+
+/// The parts of an enumeration entry that tell physical displays apart.
+#[derive(Debug)]
+struct Interface {
+    /// USB device directory, serial number or, failing both, the path
+    physical: String,
+    number: i32,
+    usage_page: u16,
+}
+
+impl Interface {
+    /// Orders the interfaces of one display: the vendor-defined usage page
+    /// the frames go to wins, then the lowest interface number.
+    fn preference(&self) -> (bool, i32) {
+        (self.usage_page < 0xff00, self.number)
+    }
+}
+
+/// Indices of the interfaces to open, one per physical display, in
+/// enumeration order.
+fn select_unique(interfaces: &[Interface]) -> Vec<usize> {
+    let mut selected: Vec<usize> = Vec::new();
+    
+    for (index, interface) in interfaces.iter().enumerate() {
+        match selected.iter_mut().find(|kept| interfaces[**kept].physical == interface.physical) {
+            Some(kept) => {
+                if interface.preference() < interfaces[*kept].preference() {
+                    *kept = index;
+                }
+            }
+            None => selected.push(index),
+        }
+    }
+    
+    selected
+}
+
+/// Takes an exclusive advisory lock on the hidraw node so that a second
+/// instance targeting the same device refuses to start instead of
+/// interleaving frames with us. The lock is released when the file is dropped.
+#[cfg(target_os = "linux")]
+fn lock_device(path: &str) -> Result<Option<File>> {
+    use std::fs::TryLockError;
+    
+    let file = File::open(path).with_context(|| format!("Failed to open {} for locking", path))?;
+    
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Err(DeviceInUse { path: path.to_string() }.into()),
+        Err(TryLockError::Error(e)) => Err(e).with_context(|| format!("Failed to lock {}", path)),
+    }
+}
+
+/// Windows (`\\?\hid#...`) and macOS (`DevSrvsID:...`) HID paths can't be
+/// opened as plain files, so there is no lock and a second instance isn't detected.
+#[cfg(not(target_os = "linux"))]
+fn lock_device(_path: &str) -> Result<Option<File>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn interface(physical: &str, number: i32, usage_page: u16) -> Interface {
+        Interface { physical: physical.to_string(), number, usage_page }
+    }
+    
+    #[test]
+    fn collections_of_one_hidraw_node_collapse() {
+        let interfaces = [
+            interface("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4", 0, 0x0001),
+            interface("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4", 0, 0xff00),
+        ];
+        
+        assert_eq!(select_unique(&interfaces), vec![1]);
+    }
+    
+    #[test]
+    fn vendor_interface_is_preferred() {
+        let interfaces = [
+            interface("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4", 0, 0x000c),
+            interface("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4", 1, 0xff00),
+        ];
+        
+        assert_eq!(select_unique(&interfaces), vec![1]);
+    }
+    
+    #[test]
+    fn lowest_interface_wins_without_vendor_usage() {
+        let interfaces = [
+            interface("serial:0001", 1, 0x0001),
+            interface("serial:0001", 0, 0x0001),
+        ];
+        
+        assert_eq!(select_unique(&interfaces), vec![1]);
+    }
+    
+    #[test]
+    fn separate_displays_are_kept_in_order() {
+        // Two units on different ports, which may share a factory default serial
+        let interfaces = [
+            interface("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4", 0, 0xff00),
+            interface("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2", 0, 0xff00),
+            interface("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-2", 1, 0x0001),
+        ];
+        
+        assert_eq!(select_unique(&interfaces), vec![0, 1]);
+    }
+}
@@ -0,0 +1,285 @@
+//! Cleanup applied to sensor readings before they reach the display.
+
+use std::collections::VecDeque;
+use tracing::warn;
+
+// Default bounds for believable sensor readings
+pub const DEFAULT_MIN_TEMP: f32 = 1.0; // A sensor reporting 0°C is glitching, not measuring
+pub const DEFAULT_MAX_TEMP: f32 = 125.0; // Above the junction limit of any desktop CPU
+pub const DEFAULT_MAX_JUMP: f32 = 30.0; // Largest believable change between two ticks
+pub const MAX_REJECTED_JUMPS: u32 = 3; // A jump that persists this many ticks is real
+
+/// Rejects readings that can't be real, so a glitching sensor doesn't flash
+/// nonsense on the display.
+pub struct ReadingValidator {
+    min: f32,
+    max: f32,
+    max_jump: f32,
+    last_valid: Option<f32>,
+    rejected_jumps: u32,
+}
+
+impl ReadingValidator {
+    /// A validator with the default bounds, which suit CPU and GPU sensors.
+    pub fn new() -> Self {
+        Self::with_bounds(DEFAULT_MIN_TEMP, DEFAULT_MAX_TEMP, DEFAULT_MAX_JUMP)
+    }
+    
+    /// Accepts readings in `min..=max` °C that change by at most `max_jump`
+    /// between two ticks, unless the jump persists.
+    pub fn with_bounds(min: f32, max: f32, max_jump: f32) -> Self {
+        Self { min, max, max_jump, last_valid: None, rejected_jumps: 0 }
+    }
+    
+    /// Returns the value to display: the reading if it passes, otherwise the
+    /// last accepted value (if there is one yet).
+    pub fn check(&mut self, temp: f32) -> Option<f32> {
+        if let Err(reason) = self.validate(temp) {
+            warn!("Rejected temperature reading {:.1}°C: {}", temp, reason);
+            return self.last_valid;
+        }
+        
+        self.last_valid = Some(temp);
+        Some(temp)
+    }
+    
+    fn validate(&mut self, temp: f32) -> Result<(), String> {
+        if !temp.is_finite() {
+            return Err("not a number".to_string());
+        }
+        
+        if !(self.min..=self.max).contains(&temp) {
+            return Err(format!("outside {:.1}..{:.1}°C", self.min, self.max));
+        }
+        
+        if let Some(last) = self.last_valid
+            && (temp - last).abs() > self.max_jump
+        {
+            // A jump that keeps being reported is a real change, not a glitch
+            self.rejected_jumps += 1;
+            if self.rejected_jumps < MAX_REJECTED_JUMPS {
+                return Err(format!("jumped {:+.1}°C since the last reading", temp - last));
+            }
+        }
+        
+        self.rejected_jumps = 0;
+        Ok(())
+    }
+    
+    /// Forgets the last accepted reading, keeping the bounds.
+    pub fn reset(&mut self) {
+        self.last_valid = None;
+        self.rejected_jumps = 0;
+    }
+}
+
+impl Default for ReadingValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Median of the last three readings. Some sensors (notably ITE Super-I/O
+/// chips) occasionally return a single garbage value that is still within
+/// the plausible range; the median drops it at the cost of one tick of delay.
+pub struct SpikeFilter {
+    window: VecDeque<f32>,
+}
+
+impl SpikeFilter {
+    const SIZE: usize = 3;
+    
+    pub fn new() -> Self {
+        Self { window: VecDeque::with_capacity(Self::SIZE) }
+    }
+    
+    pub fn apply(&mut self, temp: f32) -> f32 {
+        if self.window.len() == Self::SIZE {
+            self.window.pop_front();
+        }
+        self.window.push_back(temp);
+        
+        // Until the window fills there is no majority to compare against
+        if self.window.len() < Self::SIZE {
+            return temp;
+        }
+        
+        let mut sorted: Vec<f32> = self.window.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        sorted[Self::SIZE / 2]
+    }
+}
+
+impl Default for SpikeFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Optional smoothing, applied after the sanity checks and spike filter so
+/// the display doesn't flicker between neighbouring values every second.
+pub enum Smoother {
+    /// Exponential moving average with the weight of an N-sample average
+    Ema { alpha: f32, value: Option<f32> },
+    /// Plain average of the last N readings
+    MovingAverage { size: usize, window: VecDeque<f32> },
+}
+
+impl Smoother {
+    /// An EMA with `alpha = 2 / (window + 1)`, which centres its weight like
+    /// an average over `window` samples.
+    pub fn ema(window: usize) -> Self {
+        Smoother::Ema { alpha: 2.0 / (window.max(1) as f32 + 1.0), value: None }
+    }
+    
+    pub fn moving_average(window: usize) -> Self {
+        let size = window.max(1);
+        Smoother::MovingAverage { size, window: VecDeque::with_capacity(size) }
+    }
+    
+    pub fn apply(&mut self, temp: f32) -> f32 {
+        match self {
+            Smoother::Ema { alpha, value } => {
+                let smoothed = value.map_or(temp, |previous| previous + *alpha * (temp - previous));
+                *value = Some(smoothed);
+                smoothed
+            }
+            Smoother::MovingAverage { size, window } => {
+                if window.len() == *size {
+                    window.pop_front();
+                }
+                window.push_back(temp);
+                window.iter().sum::<f32>() / window.len() as f32
+            }
+        }
+    }
+    
+    /// Forgets the readings seen so far.
+    pub fn reset(&mut self) {
+        match self {
+            Smoother::Ema { value, .. } => *value = None,
+            Smoother::MovingAverage { window, .. } => window.clear(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn validator_rejects_readings_out_of_range() {
+        let mut validator = ReadingValidator::new();
+        assert_eq!(validator.check(0.5), None);
+        assert_eq!(validator.check(DEFAULT_MAX_TEMP + 0.1), None);
+        assert_eq!(validator.check(f32::NAN), None);
+        assert_eq!(validator.check(45.0), Some(45.0));
+        // Rejected readings fall back to the last accepted one
+        assert_eq!(validator.check(0.0), Some(45.0));
+    }
+    
+    #[test]
+    fn validator_uses_custom_bounds() {
+        let mut validator = ReadingValidator::with_bounds(-20.0, 60.0, 5.0);
+        assert_eq!(validator.check(-10.0), Some(-10.0));
+        assert_eq!(validator.check(-4.0), Some(-10.0));
+        assert_eq!(validator.check(-6.0), Some(-6.0));
+        assert_eq!(validator.check(60.5), Some(-6.0));
+    }
+    
+    #[test]
+    fn validator_rejects_a_jump() {
+        let mut validator = ReadingValidator::new();
+        assert_eq!(validator.check(40.0), Some(40.0));
+        assert_eq!(validator.check(40.0 + DEFAULT_MAX_JUMP + 1.0), Some(40.0));
+        assert_eq!(validator.check(42.0), Some(42.0));
+    }
+    
+    #[test]
+    fn validator_accepts_a_persistent_jump() {
+        let mut validator = ReadingValidator::new();
+        validator.check(40.0);
+        for _ in 1..MAX_REJECTED_JUMPS {
+            assert_eq!(validator.check(90.0), Some(40.0));
+        }
+        assert_eq!(validator.check(90.0), Some(90.0));
+        assert_eq!(validator.check(91.0), Some(91.0));
+    }
+    
+    #[test]
+    fn validator_reset_forgets_the_last_reading() {
+        let mut validator = ReadingValidator::new();
+        validator.check(40.0);
+        validator.reset();
+        assert_eq!(validator.check(90.0), Some(90.0));
+    }
+    
+    #[test]
+    fn spike_filter_passes_readings_through_while_warming_up() {
+        let mut filter = SpikeFilter::new();
+        assert_eq!(filter.apply(40.0), 40.0);
+        assert_eq!(filter.apply(90.0), 90.0);
+    }
+    
+    #[test]
+    fn spike_filter_drops_a_single_tick_spike() {
+        let mut filter = SpikeFilter::new();
+        for _ in 0..3 {
+            filter.apply(40.0);
+        }
+        assert_eq!(filter.apply(90.0), 40.0);
+        assert_eq!(filter.apply(41.0), 41.0);
+        assert_eq!(filter.apply(41.0), 41.0);
+    }
+    
+    #[test]
+    fn spike_filter_lets_a_sustained_step_through_within_two_ticks() {
+        let mut filter = SpikeFilter::new();
+        for _ in 0..3 {
+            filter.apply(40.0);
+        }
+        assert_eq!(filter.apply(60.0), 40.0);
+        assert_eq!(filter.apply(60.0), 60.0);
+        assert_eq!(filter.apply(60.0), 60.0);
+    }
+    
+    #[test]
+    fn ema_weighs_like_an_n_sample_average() {
+        let Smoother::Ema { alpha, .. } = Smoother::ema(5) else {
+            panic!("expected an EMA");
+        };
+        assert_eq!(alpha, 2.0 / 6.0);
+        
+        let mut smoother = Smoother::ema(3);
+        assert_eq!(smoother.apply(40.0), 40.0);
+        assert_eq!(smoother.apply(50.0), 45.0);
+        assert_eq!(smoother.apply(50.0), 47.5);
+    }
+    
+    #[test]
+    fn window_of_one_passes_readings_through() {
+        for mut smoother in [Smoother::ema(1), Smoother::moving_average(1)] {
+            assert_eq!(smoother.apply(40.0), 40.0);
+            assert_eq!(smoother.apply(55.0), 55.0);
+            assert_eq!(smoother.apply(42.5), 42.5);
+        }
+    }
+    
+    #[test]
+    fn moving_average_evicts_old_readings() {
+        let mut smoother = Smoother::moving_average(3);
+        assert_eq!(smoother.apply(30.0), 30.0);
+        assert_eq!(smoother.apply(60.0), 45.0);
+        assert_eq!(smoother.apply(60.0), 50.0);
+        // 30 drops out of the window
+        assert_eq!(smoother.apply(90.0), 70.0);
+    }
+    
+    #[test]
+    fn smoother_reset_forgets_readings() {
+        let mut smoother = Smoother::moving_average(3);
+        smoother.apply(30.0);
+        smoother.reset();
+        assert_eq!(smoother.apply(60.0), 60.0);
+    }
+}
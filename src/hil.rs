@@ -0,0 +1,208 @@
+//! Hardware-in-the-loop test sequence for maintainers with the physical cooler.
+//!
+//! Runs a fixed script against the connected display and writes the outcome
+//! as a JUnit XML report that CI systems can pick up.
+
+use anyhow::{Context, Result};
+use hidapi::HidApi;
+use risemode_driver::protocol::Frame;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli::Options;
+
+/// Time each swept value stays on the display, long enough to eyeball.
+const SWEEP_DWELL: Duration = Duration::from_millis(100);
+
+enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped,
+}
+
+struct Case {
+    name: &'static str,
+    time: Duration,
+    outcome: Outcome,
+}
+
+/// Runs the sequence, writes the report to `junit_path` and fails if any case did.
+pub fn run(api: &mut HidApi, options: &Options, junit_path: &Path) -> Result<()> {
+    let mut cases = Vec::new();
+    let mut devices = None;
+    
+    cases.push(timed("connect", || {
+        devices = Some(crate::open_devices(api, options)?);
+        Ok(())
+    }));
+    
+    if let Some(devices) = devices.take() {
+        cases.push(timed("sweep", || {
+            for step in 0..=100 {
+                let temp = 20.0 + step as f32 * 0.7;
+                let frame = Frame::temperature(temp)?;
+                for device in &devices {
+                    device.send(&frame).with_context(|| format!("at {:.1}°C", temp))?;
+                }
+                thread::sleep(SWEEP_DWELL);
+            }
+            Ok(())
+        }));
+        
+        cases.push(timed("blank", || {
+            for device in &devices {
+                device.send(&Frame::new())?;
+            }
+            Ok(())
+        }));
+        
+        // Close every handle (and lock) before reopening, like a replug would
+        let count = devices.len();
+        drop(devices);
+        
+        cases.push(timed("reconnect", || {
+            api.refresh_devices().context("Failed to re-enumerate HID devices")?;
+            let devices = crate::open_devices(api, options)?;
+            if devices.len() != count {
+                anyhow::bail!("expected {} device(s) after reconnecting, found {}", count, devices.len());
+            }
+            for device in &devices {
+                device.send(&Frame::temperature(42.0)?)?;
+            }
+            Ok(())
+        }));
+    } else {
+        for name in ["sweep", "blank", "reconnect"] {
+            cases.push(Case { name, time: Duration::ZERO, outcome: Outcome::Skipped });
+        }
+    }
+    
+    for case in &cases {
+        match &case.outcome {
+            Outcome::Passed => println!("PASS {} ({} ms)", case.name, case.time.as_millis()),
+            Outcome::Failed(message) => println!("FAIL {}: {}", case.name, message),
+            Outcome::Skipped => println!("SKIP {}", case.name),
+        }
+    }
+    
+    fs::write(junit_path, junit_report(&cases))
+        .with_context(|| format!("Failed to write {}", junit_path.display()))?;
+    println!("Wrote {}", junit_path.display());
+    
+    let failures = cases.iter().filter(|case| matches!(case.outcome, Outcome::Failed(_))).count();
+    if failures > 0 {
+        anyhow::bail!("{} of {} hardware test(s) failed", failures, cases.len());
+    }
+    Ok(())
+}
+
+fn timed(name: &'static str, test: impl FnOnce() -> Result<()>) -> Case {
+    let start = Instant::now();
+    let outcome = match test() {
+        Ok(()) => Outcome::Passed,
+        Err(e) => Outcome::Failed(format!("{:#}", e)),
+    };
+    Case { name, time: start.elapsed(), outcome }
+}
+
+fn junit_report(cases: &[Case]) -> String {
+    let failures = cases.iter().filter(|case| matches!(case.outcome, Outcome::Failed(_))).count();
+    let skipped = cases.iter().filter(|case| matches!(case.outcome, Outcome::Skipped)).count();
+    let total: Duration = cases.iter().map(|case| case.time).sum();
+    
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuite name=\"risemode-hil\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        cases.len(),
+        failures,
+        skipped,
+        total.as_secs_f64()
+    );
+    
+    for case in cases {
+        let _ = write!(
+            xml,
+            "  <testcase classname=\"risemode.hil\" name=\"{}\" time=\"{:.3}\"",
+            case.name,
+            case.time.as_secs_f64()
+        );
+        match &case.outcome {
+            Outcome::Passed => xml.push_str("/>\n"),
+            Outcome::Failed(message) => {
+                let _ = writeln!(xml, ">\n    <failure message=\"{}\"/>\n  </testcase>", escape_xml(message));
+            }
+            Outcome::Skipped => xml.push_str(">\n    <skipped/>\n  </testcase>\n"),
+        }
+    }
+    
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+/// Escapes `text` for an attribute value. Control characters other than
+/// tab, LF and CR aren't allowed in XML 1.0 at all, so they become U+FFFD.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < '\u{20}' => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn report_has_one_testcase_per_case() {
+        let cases = [
+            Case { name: "connect", time: Duration::from_millis(1500), outcome: Outcome::Passed },
+            Case {
+                name: "sweep",
+                time: Duration::from_millis(250),
+                outcome: Outcome::Failed("write <timeout>".to_string()),
+            },
+            Case { name: "reopen", time: Duration::ZERO, outcome: Outcome::Skipped },
+        ];
+        
+        assert_eq!(
+            junit_report(&cases),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<testsuite name=\"risemode-hil\" tests=\"3\" failures=\"1\" skipped=\"1\" time=\"1.750\">\n",
+                "  <testcase classname=\"risemode.hil\" name=\"connect\" time=\"1.500\"/>\n",
+                "  <testcase classname=\"risemode.hil\" name=\"sweep\" time=\"0.250\">\n",
+                "    <failure message=\"write &lt;timeout&gt;\"/>\n",
+                "  </testcase>\n",
+                "  <testcase classname=\"risemode.hil\" name=\"reopen\" time=\"0.000\">\n",
+                "    <skipped/>\n",
+                "  </testcase>\n",
+                "</testsuite>\n",
+            )
+        );
+    }
+    
+    #[test]
+    fn markup_is_escaped() {
+        assert_eq!(escape_xml(r#"a & "b" <c>"#), "a &amp; &quot;b&quot; &lt;c&gt;");
+        assert_eq!(escape_xml("Broken pipe (os error 32)"), "Broken pipe (os error 32)");
+    }
+    
+    #[test]
+    fn control_characters_are_replaced() {
+        assert_eq!(escape_xml("tab\tline\r\n"), "tab\tline\r\n");
+        assert_eq!(escape_xml("nul\0 esc\x1b bell\x07"), "nul\u{fffd} esc\u{fffd} bell\u{fffd}");
+    }
+}
@@ -0,0 +1,143 @@
+//! Kernel uevents for the display being plugged in or removed, so the driver
+//! can react right away instead of polling.
+//!
+//! Only available on Linux; elsewhere [`HotplugMonitor::new`] fails and
+//! callers fall back to polling.
+
+use std::io;
+use std::time::Duration;
+
+/// Something happened to a display matching the monitored vendor/product ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotplugEvent {
+    Added,
+    Removed,
+}
+
+/// Listens for `hid` uevents from the kernel. Events arrive before udev has
+/// created the hidraw node and applied permissions, so give the device a
+/// moment before opening it.
+pub struct HotplugMonitor {
+    #[cfg(target_os = "linux")]
+    socket: std::os::fd::OwnedFd,
+    /// `HID_ID` of the display, e.g. `0003:0000AA88:00008666`
+    #[cfg(target_os = "linux")]
+    hid_id: String,
+}
+
+#[cfg(target_os = "linux")]
+impl HotplugMonitor {
+    pub fn new(vendor_id: u16, product_id: u16) -> io::Result<Self> {
+        use std::os::fd::{FromRawFd, OwnedFd};
+        
+        // SAFETY: plain socket calls; the descriptor is owned right after creation
+        let socket = unsafe {
+            let fd = libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_KOBJECT_UEVENT,
+            );
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let socket = OwnedFd::from_raw_fd(fd);
+            
+            let mut address: libc::sockaddr_nl = std::mem::zeroed();
+            address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            // Multicast group 1 carries the kernel's own events
+            address.nl_groups = 1;
+            if libc::bind(
+                fd,
+                (&address as *const libc::sockaddr_nl).cast(),
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            ) < 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            
+            socket
+        };
+        
+        // hid-core reports the bus as 0003 (USB) and pads both IDs to 8 digits
+        let hid_id = format!("0003:{:08X}:{:08X}", vendor_id, product_id);
+        Ok(Self { socket, hid_id })
+    }
+    
+    /// Waits up to `timeout` for the display to be added or removed. Returns
+    /// `None` on timeout; unrelated events are skipped.
+    pub fn wait(&self, timeout: Duration) -> io::Result<Option<HotplugEvent>> {
+        use std::os::fd::AsRawFd;
+        use std::time::Instant;
+        
+        let deadline = Instant::now() + timeout;
+        let mut buffer = [0u8; 8192];
+        
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let mut poll_fd = libc::pollfd { fd: self.socket.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            
+            // SAFETY: poll_fd is valid for the duration of the call
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, remaining.as_millis().min(i32::MAX as u128) as i32) };
+            if ready < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            if ready == 0 {
+                return Ok(None);
+            }
+            
+            // SAFETY: the buffer outlives the call and its length is passed along
+            let len = unsafe { libc::recv(self.socket.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len(), 0) };
+            if len < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            
+            if let Some(event) = self.parse(&buffer[..len as usize]) {
+                return Ok(Some(event));
+            }
+        }
+    }
+    
+    /// Parses an `add@/devices/...\0ACTION=add\0SUBSYSTEM=hid\0...` message.
+    fn parse(&self, message: &[u8]) -> Option<HotplugEvent> {
+        let mut action = None;
+        let mut subsystem = None;
+        let mut hid_id = None;
+        
+        for field in message.split(|&byte| byte == 0) {
+            let field = std::str::from_utf8(field).ok()?;
+            if let Some((key, value)) = field.split_once('=') {
+                match key {
+                    "ACTION" => action = Some(value),
+                    "SUBSYSTEM" => subsystem = Some(value),
+                    "HID_ID" => hid_id = Some(value),
+                    _ => {}
+                }
+            }
+        }
+        
+        if subsystem != Some("hid") || !hid_id.is_some_and(|id| id.eq_ignore_ascii_case(&self.hid_id)) {
+            return None;
+        }
+        
+        match action? {
+            "add" => Some(HotplugEvent::Added),
+            "remove" => Some(HotplugEvent::Removed),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl HotplugMonitor {
+    pub fn new(_vendor_id: u16, _product_id: u16) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "hotplug events are only available on Linux"))
+    }
+    
+    pub fn wait(&self, _timeout: Duration) -> io::Result<Option<HotplugEvent>> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,94 @@
+//! `risemode-driver install`: first-time setup of the udev rule and the
+//! systemd service on Linux.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use crate::cli::Options;
+
+const RULE_PATH: &str = "etc/udev/rules.d/99-risemode.rules";
+const UNIT_PATH: &str = "etc/systemd/system/risemode-driver.service";
+
+/// The packaged unit, with `ExecStart=` pointed at the running binary.
+const UNIT_TEMPLATE: &str = include_str!("../packaging/arch/risemode-driver.service");
+
+fn udev_rule(options: &Options) -> String {
+    // uaccess gives the logged-in desktop user access, for running without the service
+    format!(
+        "# Rise Mode Aura Ice Black water cooler, written by risemode-driver install\n\
+         SUBSYSTEM==\"hidraw\", ATTRS{{idVendor}}==\"{:04x}\", ATTRS{{idProduct}}==\"{:04x}\", MODE=\"0660\", TAG+=\"uaccess\"\n",
+        options.vendor_id(),
+        options.product_id()
+    )
+}
+
+fn unit(binary: &Path) -> String {
+    UNIT_TEMPLATE.replace("ExecStart=/usr/bin/risemode-driver", &format!("ExecStart={}", binary.display()))
+}
+
+fn write(root: &Path, relative: &str, contents: &str, force: bool) -> Result<()> {
+    let path = root.join(relative);
+    if path.exists() && !force {
+        anyhow::bail!("{} already exists, pass --force to replace it", path.display());
+    }
+    
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn command(program: &str, args: &[&str]) -> Result<()> {
+    println!("Running {} {}", program, args.join(" "));
+    let status = process::Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !status.success() {
+        anyhow::bail!("{} {} failed ({})", program, args.join(" "), status);
+    }
+    Ok(())
+}
+
+/// Writes the rule and the unit below `root`. Reloading udev and systemd,
+/// and enabling the service, only happen when installing into the live
+/// system (`root` is `/`).
+pub fn run(options: &Options, root: &Path, enable: bool, force: bool) -> Result<()> {
+    if !cfg!(target_os = "linux") {
+        anyhow::bail!("install sets up udev and systemd, which only exist on Linux");
+    }
+    
+    let binary = env::current_exe().context("Could not find the path of this binary")?;
+    if binary.starts_with("/home") || binary.starts_with("/root") {
+        // The unit sets ProtectHome=true, so the service could not start it
+        eprintln!(
+            "Warning: {} is in a home directory, which the service can't see; copy it to /usr/local/bin and run install from there",
+            binary.display()
+        );
+    }
+    write(root, RULE_PATH, &udev_rule(options), force)?;
+    write(root, UNIT_PATH, &unit(&binary), force)?;
+    
+    if root != Path::new("/") {
+        if enable {
+            eprintln!("Warning: Not enabling the service outside the live system (--root {})", root.display());
+        }
+        return Ok(());
+    }
+    
+    command("udevadm", &["control", "--reload-rules"])?;
+    command("udevadm", &["trigger", "--subsystem-match=hidraw"])?;
+    command("systemctl", &["daemon-reload"])?;
+    if enable {
+        command("systemctl", &["enable", "--now", "risemode-driver.service"])?;
+    } else {
+        println!("Start the driver at boot with: sudo systemctl enable --now risemode-driver");
+    }
+    
+    Ok(())
+}
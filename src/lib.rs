@@ -0,0 +1,43 @@
+//! Driver for the Rise Mode Aura Ice Black water cooler's temperature display.
+//!
+//! The display is a USB HID device that shows whatever temperature it is
+//! sent. [`RisemodeDevice`] opens and writes to it, and [`TemperatureSource`]
+//! is the interface for anything that can provide a reading.
+//!
+//! ```no_run
+//! use hidapi::HidApi;
+//! use risemode_driver::protocol::Frame;
+//! use risemode_driver::{FallbackSource, RisemodeDevice, TemperatureSource};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let api = HidApi::new()?;
+//! let devices = RisemodeDevice::open_all(&api)?;
+//! let mut cpu = FallbackSource::cpu();
+//!
+//! let temp = cpu.read()?;
+//! for device in &devices {
+//!     device.send(&Frame::temperature(temp)?)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod device;
+pub mod filter;
+pub mod hotplug;
+pub mod load;
+pub mod protocol;
+pub mod sensors;
+#[cfg(feature = "test-util")]
+pub mod testing;
+pub mod transport;
+pub mod usb;
+
+pub use device::{DeviceInUse, DeviceSelector, RisemodeDevice, PRODUCT_ID, VENDOR_ID};
+pub use hotplug::{HotplugEvent, HotplugMonitor};
+pub use load::{CpuLoadSource, MemoryUsageSource};
+pub use transport::HidTransport;
+pub use sensors::{
+    AmdGpuSource, FallbackSource, GpuTemperature, HottestSource, HwmonAggregate, HwmonSource, SourceHealth,
+    TemperatureSource, ThermalZoneSource,
+};
@@ -0,0 +1,100 @@
+//! CPU and memory utilisation from `/proc`, shown on the display instead of
+//! a temperature with `--metric load` or `--metric memory`.
+
+use anyhow::{Context, Result};
+use std::fs;
+
+use crate::sensors::TemperatureSource;
+
+/// Cumulative jiffies from the aggregate `cpu` line of `/proc/stat`.
+#[derive(Debug, Clone, Copy)]
+struct CpuTimes {
+    idle: u64,
+    total: u64,
+}
+
+fn read_cpu_times() -> Result<CpuTimes> {
+    let stat = fs::read_to_string("/proc/stat").context("Failed to read /proc/stat")?;
+    let line = stat
+        .lines()
+        .find(|line| line.starts_with("cpu "))
+        .context("No aggregate cpu line in /proc/stat")?;
+    
+    // user nice system idle iowait irq softirq steal; guest time is already
+    // included in user
+    let fields: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .take(8)
+        .map(|field| field.parse().context("Malformed cpu line in /proc/stat"))
+        .collect::<Result<_>>()?;
+    if fields.len() < 4 {
+        anyhow::bail!("Malformed cpu line in /proc/stat");
+    }
+    
+    let iowait = fields.get(4).copied().unwrap_or(0);
+    Ok(CpuTimes { idle: fields[3] + iowait, total: fields.iter().sum() })
+}
+
+/// Overall CPU load in percent (0–100), averaged over all cores since the
+/// previous reading. The first reading averages since boot.
+#[derive(Debug, Default)]
+pub struct CpuLoadSource {
+    previous: Option<CpuTimes>,
+}
+
+impl CpuLoadSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TemperatureSource for CpuLoadSource {
+    fn name(&self) -> &str {
+        "load"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        let now = read_cpu_times()?;
+        let previous = self.previous.replace(now).unwrap_or(CpuTimes { idle: 0, total: 0 });
+        
+        let total = now.total.saturating_sub(previous.total);
+        if total == 0 {
+            // Called again within the same jiffy
+            anyhow::bail!("No CPU time elapsed since the last reading");
+        }
+        let idle = now.idle.saturating_sub(previous.idle).min(total);
+        
+        Ok((total - idle) as f32 * 100.0 / total as f32)
+    }
+}
+
+/// Share of RAM in use in percent, from `MemTotal` and `MemAvailable` in
+/// `/proc/meminfo` (so page cache doesn't count as used).
+#[derive(Debug, Default)]
+pub struct MemoryUsageSource;
+
+fn meminfo_kib(meminfo: &str, key: &str) -> Result<u64> {
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .and_then(|value| value.split_whitespace().next()?.parse().ok())
+        .with_context(|| format!("No {} in /proc/meminfo", key))
+}
+
+impl TemperatureSource for MemoryUsageSource {
+    fn name(&self) -> &str {
+        "memory"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        let meminfo = fs::read_to_string("/proc/meminfo").context("Failed to read /proc/meminfo")?;
+        let total = meminfo_kib(&meminfo, "MemTotal")?;
+        let available = meminfo_kib(&meminfo, "MemAvailable")?.min(total);
+        if total == 0 {
+            anyhow::bail!("MemTotal is zero in /proc/meminfo");
+        }
+        
+        Ok((total - available) as f32 * 100.0 / total as f32)
+    }
+}
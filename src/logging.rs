@@ -0,0 +1,57 @@
+//! Log output of the driver, through `tracing`.
+//!
+//! Command results (`list-devices`, `probe`, `info`...) are printed to
+//! stdout as before; everything the driver reports while working goes
+//! through here, to stderr or the systemd journal.
+
+use std::env;
+use std::io::{self, IsTerminal};
+use tracing::level_filters::LevelFilter;
+
+use crate::cli::{LogFormat, Options};
+
+/// Installs the global subscriber for `--log-level` and `--log-format`.
+pub fn init(options: &Options) {
+    let level = LevelFilter::from(options.log_level);
+    
+    let format = match options.log_format {
+        // systemd sets JOURNAL_STREAM when stderr is connected to the journal
+        LogFormat::Auto if env::var_os("JOURNAL_STREAM").is_some() => LogFormat::Journald,
+        LogFormat::Auto => LogFormat::Text,
+        format => format,
+    };
+    
+    match format {
+        LogFormat::Json => tracing_subscriber::fmt().json().with_max_level(level).with_writer(io::stderr).init(),
+        LogFormat::Journald => init_journald(level),
+        _ => init_text(level),
+    }
+}
+
+fn init_text(level: LevelFilter) {
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_target(false)
+        .with_ansi(io::stderr().is_terminal())
+        .with_writer(io::stderr)
+        .init();
+}
+
+#[cfg(target_os = "linux")]
+fn init_journald(level: LevelFilter) {
+    use tracing_subscriber::prelude::*;
+    
+    match tracing_journald::layer() {
+        Ok(layer) => tracing_subscriber::registry().with(layer.with_filter(level)).init(),
+        Err(e) => {
+            init_text(level);
+            tracing::warn!("Could not connect to the systemd journal, logging to stderr: {}", e);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn init_journald(level: LevelFilter) {
+    init_text(level);
+    tracing::warn!("The systemd journal only exists on Linux, logging to stderr");
+}
@@ -1,169 +1,1224 @@
+mod cli;
+mod dbus;
+mod hil;
+mod install;
+mod logging;
+mod metrics;
+mod mqtt;
+mod nicknames;
+mod notify;
+mod recovery;
+mod sandbox;
+mod script;
+mod signals;
+mod soak;
+mod textfile;
+mod usage;
+
 use anyhow::{Context, Result};
-use hidapi::HidApi;
-use std::fs;
-use std::time::Duration;
+use clap::Parser;
+use cli::{Cli, Command, Metric, Options, SensorKind};
+use hidapi::{DeviceInfo, HidApi};
+use risemode_driver::sensors::{
+    self, AmdGpuSource, FallbackSource, HottestSource, HwmonSource, SensorsConfig, TemperatureSource,
+    ThermalZoneSource,
+};
+use notify::Notifier;
+use recovery::Recovery;
+use metrics::{Channel, Rotation};
+use dbus::DbusService;
+use mqtt::Mqtt;
+use textfile::Textfile;
+use risemode_driver::protocol::{Frame, InputReport, FRAME_LEN, MAX_TEMPERATURE};
+use risemode_driver::{device, usb, DeviceInUse, DeviceSelector, HotplugEvent, HotplugMonitor, RisemodeDevice};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+use usage::{Counters, Usage};
 use std::thread;
+use tracing::{debug, error, info, warn};
+
+// A tick that takes this many intervals (and at least MIN_GAP) means the
+// machine was suspended or the process stopped, rather than running late
+const GAP_INTERVALS: u32 = 5;
+const MIN_GAP: Duration = Duration::from_secs(5);
+// Reports read from one display per tick at most, so a chatty one can't
+// delay the next update
+const MAX_READS_PER_TICK: usize = 16;
+// How long `probe` listens for a report from each display
+const PROBE_READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Startup progress of the sender. Frames are only written once a valid
+/// reading exists, so the display never shows a value we didn't measure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Connecting,
+    WaitingForSensor,
+    Streaming,
+}
+
+/// Why the send loop returned without an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    /// SIGTERM or SIGINT: shut down
+    Stop,
+    /// SIGHUP: reopen the displays and sensors
+    Reload,
+}
+
+/// Blanks the display once the system has been idle (below a threshold) for
+/// a while, and wakes it on the first reading above it.
+struct IdleBlanker {
+    threshold: f32,
+    delay: Duration,
+    idle_since: Option<Instant>,
+    blanked: bool,
+}
+
+impl IdleBlanker {
+    fn new(threshold: f32, delay: Duration) -> Self {
+        Self { threshold, delay, idle_since: None, blanked: false }
+    }
+    
+    /// Feeds a reading taken at `now` and returns whether the display should be blank.
+    fn update(&mut self, temp: f32, now: Instant) -> bool {
+        if temp >= self.threshold {
+            if self.blanked {
+                info!("Activity detected ({:.1}°C), waking display", temp);
+            }
+            self.idle_since = None;
+            self.blanked = false;
+            return false;
+        }
+        
+        let idle_since = *self.idle_since.get_or_insert(now);
+        if !self.blanked && now.duration_since(idle_since) >= self.delay {
+            info!(
+                "Idle below {:.1}°C for {} s, blanking display",
+                self.threshold,
+                self.delay.as_secs()
+            );
+            self.blanked = true;
+        }
+        
+        self.blanked
+    }
+}
+
+/// `--ramp-up`: eases the displayed value in from 0 over the first seconds
+/// after connecting, instead of jumping there from whatever the firmware
+/// showed at power-on.
+struct Ramp {
+    duration: Duration,
+    started: Option<Instant>,
+}
+
+impl Ramp {
+    fn new(duration: Duration) -> Self {
+        Self { duration, started: None }
+    }
+    
+    /// Scales `value` by how far into the ramp we are at `now`. The ramp
+    /// starts with the first value, so a slow sensor doesn't use it up.
+    fn apply(&mut self, value: f32, now: Instant) -> f32 {
+        let started = *self.started.get_or_insert(now);
+        let progress = (now.saturating_duration_since(started).as_secs_f32() / self.duration.as_secs_f32()).min(1.0);
+        // Smoothstep, so the value neither starts nor stops moving abruptly
+        value * progress * progress * (3.0 - 2.0 * progress)
+    }
+}
+
+/// Everything besides the displays that the send loop reports to or takes
+/// commands from. Each is optional.
+struct Integrations {
+    notifier: Option<Notifier>,
+    mqtt: Option<Mqtt>,
+    dbus: Option<DbusService>,
+    textfile: Option<Textfile>,
+}
+
+impl Integrations {
+    /// A short state such as `streaming`, for MQTT and D-Bus.
+    fn state(&mut self, state: &str) {
+        if let Some(mqtt) = &mut self.mqtt {
+            mqtt.status(state);
+        }
+        if let Some(dbus) = &self.dbus {
+            dbus.set_state(state);
+        }
+        if let Some(textfile) = &mut self.textfile {
+            textfile.state(state);
+        }
+    }
+    
+    /// Brings the metrics file up to date, once per tick.
+    fn write_metrics(&mut self, counters: &Counters, displays: usize) {
+        if let Some(textfile) = &mut self.textfile {
+            textfile.write(counters, displays);
+        }
+    }
+    
+    /// A line for `systemctl status`, also offered over D-Bus.
+    fn status(&self, status: &str) {
+        if let Some(notifier) = &self.notifier {
+            notifier.status(status);
+        }
+        if let Some(dbus) = &self.dbus {
+            dbus.set_status(status);
+        }
+    }
+    
+    fn is_paused(&self) -> bool {
+        self.dbus.as_ref().is_some_and(DbusService::is_paused)
+    }
+}
 
-// Vendor ID and Product ID for the water cooler display
-const VENDOR_ID: u16 = 0xaa88; // 43656 in decimal
-const PRODUCT_ID: u16 = 0x8666; // 34406 in decimal
-const UPDATE_INTERVAL_MS: u64 = 1000; // Update every 1 second
+/// What one group of displays shows: the `--metric` list for displays
+/// without a `--device-metric`, or the metrics given for a named display.
+struct Route {
+    /// Display name from `--device-metric`; `None` for the default route
+    name: Option<String>,
+    rotation: Rotation,
+    idle_blanker: Option<IdleBlanker>,
+    ramp: Option<Ramp>,
+}
+
+impl Route {
+    /// `None` if `metrics` is empty.
+    fn new(name: Option<String>, metrics: &[Metric], options: &Options) -> Option<Self> {
+        let idle_blanker = options
+            .idle_blank_below
+            .map(|threshold| IdleBlanker::new(threshold, Duration::from_secs(options.idle_blank_after)));
+        let ramp = (options.ramp_up > 0).then(|| Ramp::new(Duration::from_secs(options.ramp_up)));
+        
+        Some(Self { name, rotation: Rotation::new(metrics, options)?, idle_blanker, ramp })
+    }
+    
+    fn serves(&self, device: &RisemodeDevice, routes: &[Route]) -> bool {
+        match &self.name {
+            Some(name) => device.name() == name,
+            // The default route gets every display no other route claims
+            None => !routes.iter().any(|route| route.name.as_deref() == Some(device.name())),
+        }
+    }
+}
+
+/// Builds the frame for a reading on a route: the value as shown in
+/// `--unit`, unless the idle blanker says to blank the display. The value
+/// returned is the reading itself, while the frame follows `--ramp-up`.
+fn encode(
+    channel: &Channel,
+    idle_blanker: Option<&mut IdleBlanker>,
+    ramp: Option<&mut Ramp>,
+    temp: f32,
+    options: &Options,
+) -> (f32, Frame) {
+    let now = Instant::now();
+    // Only temperatures tell whether the machine is idle
+    let blank = idle_blanker
+        .is_some_and(|blanker| if channel.is_temperature() { blanker.update(temp, now) } else { blanker.blanked });
+    let shown = channel.displayed(temp, options);
+    
+    if blank {
+        return (shown, Frame::new());
+    }
+    let sent = match ramp {
+        Some(ramp) => ramp.apply(shown, now),
+        None => shown,
+    };
+    // Values past the frame's range, e.g. a hot CPU in °F, show the nearest one it can hold
+    (shown, Frame::temperature(sent.clamp(0.0, MAX_TEMPERATURE)).unwrap_or_default())
+}
+
+/// One route per `--device-metric`, plus the default one.
+fn routes(options: &Options) -> Result<Vec<Route>> {
+    let mut routes = vec![Route::new(None, &options.metric, options).context("--metric names no metric to show")?];
+    
+    for device_metric in &options.device_metric {
+        let mut named = options.devices.iter().chain(&options.nicknames);
+        if !named.any(|spec| spec.name.as_deref() == Some(device_metric.name.as_str())) {
+            warn!("--device-metric {} names no display given with --device or a nickname", device_metric.name);
+        }
+        let route = Route::new(Some(device_metric.name.clone()), &device_metric.metrics, options)
+            .with_context(|| format!("--device-metric {} names no metric to show", device_metric.name))?;
+        routes.push(route);
+    }
+    
+    Ok(routes)
+}
 
 fn main() -> Result<()> {
-    println!("SendTemp (Rust version) - Starting...");
-    println!("Reading CPU temperature and sending to water cooler display");
+    let mut cli = Cli::parse();
+    logging::init(&cli.options);
+    if cli.options.min_temp >= cli.options.max_temp {
+        anyhow::bail!("--min-temp must be below --max-temp");
+    }
+    if cli.options.max_jump <= 0.0 {
+        anyhow::bail!("--max-jump must be above 0");
+    }
+    cli.options.nicknames = nicknames::load().unwrap_or_else(|e| {
+        warn!("Ignoring display nicknames: {:#}", e);
+        Vec::new()
+    });
     
     // Initialize HID API
-    let api = HidApi::new().context("Failed to initialize HID API")?;
+    let mut api = HidApi::new().context("Failed to initialize HID API")?;
+    
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(&mut api, &cli.options),
+        Command::ListDevices => list_devices(&api, &cli.options),
+        Command::Nickname { name, selector, remove } => nicknames::run(name, selector, remove),
+        Command::Send { temp } => send(&api, &cli.options, temp),
+        Command::SendRaw { bytes, no_pad } => send_raw(&api, &cli.options, &bytes, no_pad),
+        Command::Identify { display, seconds } => identify(&api, &cli.options, &display, seconds),
+        Command::Probe => probe(&api, &cli.options),
+        Command::Sensors => list_sensors(),
+        Command::Script { file } => script::run(&api, &cli.options, &file),
+        Command::PowerCycle => power_cycle(&mut api, &cli.options),
+        Command::Info => usage::print_info(),
+        Command::HilTest { junit } => hil::run(&mut api, &cli.options, &junit),
+        Command::Soak { rate, hours } => soak::run(&mut api, &cli.options, rate, hours),
+        Command::Install { root, enable, force } => install::run(&cli.options, &root, enable, force),
+    }
+}
+
+fn run(api: &mut HidApi, options: &Options) -> Result<()> {
+    info!("SendTemp (Rust version) - Starting...");
+    if options.dry_run {
+        return dry_run(options);
+    }
+    info!("Reading CPU temperature and sending to water cooler display");
+    
+    // Failure counts, kept across reconnects
+    let mut recovery = Recovery::new();
+    let mut usage = Usage::start();
+    let hotplug = match HotplugMonitor::new(options.vendor_id(), options.product_id()) {
+        Ok(hotplug) => Some(hotplug),
+        Err(e) => {
+            warn!("No hotplug events, polling for the display instead: {}", e);
+            None
+        }
+    };
+    
+    if let Err(e) = signals::install() {
+        warn!("Could not install signal handlers: {}", e);
+    }
+    let mut integrations = Integrations {
+        notifier: Notifier::from_env(),
+        mqtt: Mqtt::new(options)?,
+        dbus: options.dbus.map(|bus| DbusService::start(bus, &options.metric)).transpose()?,
+        textfile: options.textfile.as_deref().map(Textfile::new),
+    };
+    
+    if options.sandbox {
+        enable_sandbox(options)?;
+    }
     
     // Keep trying to connect to devices
     loop {
-        match run_temperature_sender(&api) {
-            Ok(_) => {
-                println!("Temperature sender stopped normally");
+        let result =
+            run_temperature_sender(api, options, &mut recovery, &mut usage, hotplug.as_ref(), &mut integrations);
+        usage.maybe_save();
+        
+        match result {
+            Ok(Exit::Stop) => {
+                info!("Temperature sender stopped normally");
                 break;
             }
+            Ok(Exit::Reload) => {
+                info!("Reloading: reopening displays and sensors");
+                if let Some(notifier) = &integrations.notifier {
+                    notifier.reloading();
+                }
+                recovery::refresh(api);
+            }
+            Err(e) if e.is::<DeviceInUse>() => {
+                // Two daemons would interleave frames on the display, so refuse to run
+                return Err(e);
+            }
             Err(e) => {
-                eprintln!("Error: {}. Retrying in 1 second...", e);
-                thread::sleep(Duration::from_secs(1));
+                error!("{:#}", e);
+                integrations.state("reconnecting");
+                integrations.status(&format!("Recovering: {:#}", e));
+                integrations.write_metrics(&usage.counters, 0);
+                if let Some(notifier) = &integrations.notifier {
+                    // Waiting for the display is not a wedged loop
+                    notifier.watchdog();
+                }
+                if signals::stop_requested() {
+                    break;
+                }
+                recovery.escalate(api, options, &mut usage, hotplug.as_ref());
+                if signals::stop_requested() {
+                    break;
+                }
             }
         }
     }
     
+    if let Some(notifier) = &integrations.notifier {
+        notifier.stopping();
+    }
+    integrations.state("stopped");
+    integrations.write_metrics(&usage.counters, 0);
+    if let Some(mqtt) = &mut integrations.mqtt {
+        mqtt.disconnect();
+    }
+    usage.save();
     Ok(())
 }
 
-fn run_temperature_sender(api: &HidApi) -> Result<()> {
-    // Find and connect to HID devices with matching vendor/product ID
-    let mut devices = Vec::new();
+/// `--dry-run`: reads the sensors and builds frames like [`run`], but prints
+/// them as hex instead of opening any display.
+fn dry_run(options: &Options) -> Result<()> {
+    info!("Dry run, printing frames instead of sending them");
+    if let Err(e) = signals::install() {
+        warn!("Could not install signal handlers: {}", e);
+    }
     
-    println!("Searching for HID devices (VID: 0x{:04x}, PID: 0x{:04x})...", VENDOR_ID, PRODUCT_ID);
+    let interval = Duration::from_millis(options.interval);
+    let mut routes = routes(options)?;
+    let mut next_tick = Instant::now();
     
-    for device_info in api.device_list() {
-        if device_info.vendor_id() == VENDOR_ID && device_info.product_id() == PRODUCT_ID {
-            println!("Found device: {:?}", device_info.path());
-            match device_info.open_device(api) {
-                Ok(device) => {
-                    println!("Successfully opened HID device");
-                    devices.push(device);
-                }
-                Err(e) => {
-                    eprintln!("Failed to open device: {}", e);
+    while !signals::stop_requested() {
+        for route in &mut routes {
+            let channel = route.rotation.current(Instant::now());
+            match channel.read(options) {
+                Ok(None) => {}
+                Ok(Some(temp)) => {
+                    let (shown, frame) =
+                        encode(channel, route.idle_blanker.as_mut(), route.ramp.as_mut(), temp, options);
+                    let prefix = route.name.as_deref().map(|name| format!("[{}] ", name)).unwrap_or_default();
+                    println!("{}{}: {:.1}{} -> {}", prefix, channel.label, shown, channel.unit, frame);
                 }
+                Err(e) => warn!("Failed to read temperature: {}", e),
             }
         }
+        
+        next_tick += interval;
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        }
     }
     
-    if devices.is_empty() {
+    Ok(())
+}
+
+/// Applies the Landlock sandbox once everything needing wider access is set up.
+fn enable_sandbox(options: &Options) -> Result<()> {
+    let state_dir = usage::state_dir();
+    std::fs::create_dir_all(&state_dir).with_context(|| format!("Failed to create {}", state_dir.display()))?;
+    let textfile_dir = options.textfile.as_deref().map(Textfile::dir);
+    
+    if sandbox::apply(&state_dir, textfile_dir, options.mqtt.is_some()).context("Failed to enable the sandbox")? {
+        info!("Sandbox enabled");
+    } else {
+        warn!("Landlock is not supported by this kernel, running without a sandbox");
+    }
+    
+    Ok(())
+}
+
+fn hwmon_source(options: &Options) -> HwmonSource {
+    let mut source = match &options.hwmon_device {
+        Some(device) => HwmonSource::with_device(device.as_str()),
+        None => HwmonSource::new(),
+    };
+    
+    if let Some(chip) = &options.hwmon_name {
+        source = source.chip(chip.as_str());
+    }
+    if let Some(input) = &options.hwmon_input {
+        source = source.input(input.as_str());
+    }
+    if let Some(label) = &options.hwmon_label {
+        source = source.label(label.as_str()).sensors_config(SensorsConfig::load());
+    }
+    
+    source.aggregate(options.hwmon_aggregate.into())
+}
+
+/// The CPU chain used by `--sensor auto`.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn cpu_source(options: &Options) -> FallbackSource {
+    FallbackSource::new(vec![Box::new(hwmon_source(options)), Box::new(ThermalZoneSource)])
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+fn cpu_source(_options: &Options) -> FallbackSource {
+    FallbackSource::cpu()
+}
+
+fn temperature_source(options: &Options) -> Box<dyn TemperatureSource> {
+    let cpu = cpu_source(options);
+    let gpu = AmdGpuSource::new(options.gpu_temp.into());
+    
+    match options.sensor {
+        SensorKind::Auto => Box::new(cpu),
+        SensorKind::Hwmon => Box::new(hwmon_source(options)),
+        SensorKind::Thermal => Box::new(ThermalZoneSource),
+        SensorKind::Amdgpu => Box::new(gpu),
+        SensorKind::Hottest => Box::new(HottestSource::new(vec![Box::new(cpu), Box::new(gpu)])),
+    }
+}
+
+/// Whether `--device` allows this display, and the name to give it: the
+/// one from `--device`, or else its nickname.
+fn selected(options: &Options, info: &DeviceInfo) -> Option<Option<String>> {
+    let nickname =
+        options.nicknames.iter().find(|spec| spec.selector.matches(info)).and_then(|spec| spec.name.clone());
+    if options.devices.is_empty() {
+        return Some(nickname);
+    }
+    
+    let spec = options.devices.iter().find(|spec| spec.selector.matches(info))?;
+    Some(spec.name.clone().or(nickname))
+}
+
+fn open_device(api: &HidApi, info: &DeviceInfo, name: Option<String>) -> Result<RisemodeDevice> {
+    let mut device = RisemodeDevice::open(api, info)?;
+    if let Some(name) = name {
+        device.set_name(name);
+    }
+    
+    Ok(device)
+}
+
+fn open_devices(api: &HidApi, options: &Options) -> Result<Vec<RisemodeDevice>> {
+    if options.devices.is_empty() && options.nicknames.is_empty() {
+        let devices = RisemodeDevice::open_matching(api, options.vendor_id(), options.product_id())?;
+        if devices.is_empty() {
+            anyhow::bail!("No matching HID devices found");
+        }
+        return Ok(devices);
+    }
+    
+    let mut devices = Vec::new();
+    for info in device::find_displays(api, options.vendor_id(), options.product_id()) {
+        let Some(name) = selected(options, info) else {
+            debug!("Skipping {}, not selected with --device", info.path().to_string_lossy());
+            continue;
+        };
+        
+        match open_device(api, info, name) {
+            Ok(device) => {
+                info!("Opened {}", device.name());
+                devices.push(device);
+            }
+            Err(e) if e.is::<DeviceInUse>() => return Err(e),
+            Err(e) => warn!("Failed to open device: {:#}", e),
+        }
+    }
+    
+    for spec in &options.devices {
+        let found = device::find_displays(api, options.vendor_id(), options.product_id())
+            .into_iter()
+            .any(|info| spec.selector.matches(info));
+        if !found {
+            warn!("No display matches --device {}", spec.selector);
+        }
+    }
+    
+    if devices.is_empty() && options.devices.is_empty() {
         anyhow::bail!("No matching HID devices found");
     }
+    if devices.is_empty() {
+        anyhow::bail!("None of the displays given with --device are connected");
+    }
     
-    println!("Connected to {} device(s)", devices.len());
-    println!("Starting temperature monitoring...");
+    Ok(devices)
+}
+
+fn list_devices(api: &HidApi, options: &Options) -> Result<()> {
+    let mut found = 0;
     
-    // Continuously read CPU temperature and send to devices
-    loop {
-        match read_cpu_temperature() {
-            Ok(temp) => {
-                // Create a 24-byte buffer with temperature in binary format
-                // The display expects: [temp_integer, temp_decimal, padding...]
-                let mut buffer = [0u8; 24];
-                
-                let temp_int = temp as u8;  // Integer part of temperature
-                let temp_decimal = ((temp - temp_int as f32) * 10.0) as u8;  // First decimal digit
-                
-                // Format: byte 0 = integer temp, byte 1 = decimal digit (0-9)
-                buffer[0] = temp_int;
-                buffer[1] = temp_decimal;
-                
-                println!("CPU: {:.1}°C (sending bytes: {:02x} {:02x})", temp, buffer[0], buffer[1]);
-                
-                // Send to all connected HID devices
-                for device in &devices {
-                    if let Err(e) = device.write(&buffer) {
-                        eprintln!("Failed to write to HID device: {}", e);
-                        return Err(anyhow::anyhow!("HID write failed: {}", e));
-                    }
+    for info in device::find_devices(api, options.vendor_id(), options.product_id()) {
+        found += 1;
+        println!("{}", info.path().to_string_lossy());
+        println!("  Product: {}", info.product_string().unwrap_or("unknown"));
+        println!("  Manufacturer: {}", info.manufacturer_string().unwrap_or("unknown"));
+        println!("  Serial: {}", info.serial_number().unwrap_or("none"));
+        println!("  USB port: {}", device::usb_port(info).as_deref().unwrap_or("unknown"));
+        if let Some(Some(nickname)) = selected(options, info) {
+            println!("  Name: {}", nickname);
+        }
+        println!("  Interface: {}", info.interface_number());
+    }
+    
+    if found == 0 {
+        println!(
+            "No HID devices found (VID: 0x{:04x}, PID: 0x{:04x})",
+            options.vendor_id(),
+            options.product_id()
+        );
+    }
+    
+    Ok(())
+}
+
+fn send(api: &HidApi, options: &Options, temp: f32) -> Result<()> {
+    let frame = Frame::temperature(temp)?;
+    let devices = open_devices(api, options)?;
+    
+    for device in &devices {
+        device.send(&frame)?;
+    }
+    
+    println!("Sent {:.1}{} ({}) to {} device(s)", temp, options.unit.symbol(), frame, devices.len());
+    Ok(())
+}
+
+/// Parses hex words such as `3a`, `0x3a` or `3a0100` into bytes. A quoted
+/// `"3a 01 00"` counts as several words.
+fn parse_hex(words: &[String]) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    
+    for word in words.iter().flat_map(|word| word.split_whitespace()) {
+        let digits = word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")).unwrap_or(word);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("{:?} is not hex, separate bytes with spaces", word);
+        }
+        if digits.is_empty() || digits.len() % 2 != 0 {
+            anyhow::bail!("{:?} is not a whole number of hex bytes", word);
+        }
+        for pair in digits.as_bytes().chunks(2) {
+            let pair = std::str::from_utf8(pair).unwrap_or_default();
+            bytes.push(u8::from_str_radix(pair, 16).with_context(|| format!("{:?} is not a hex byte", pair))?);
+        }
+    }
+    if bytes.is_empty() {
+        anyhow::bail!("no bytes given");
+    }
+    
+    Ok(bytes)
+}
+
+/// The report `send-raw` writes for `words`: padded to a frame, or as is
+/// with `no_pad`.
+fn raw_report(words: &[String], no_pad: bool) -> Result<Vec<u8>> {
+    let mut report = parse_hex(words)?;
+    if report.len() > FRAME_LEN && !no_pad {
+        anyhow::bail!(
+            "{} bytes don't fit in a {}-byte frame, pass --no-pad to send them as is",
+            report.len(),
+            FRAME_LEN
+        );
+    }
+    if !no_pad {
+        report.resize(FRAME_LEN, 0);
+    }
+    Ok(report)
+}
+
+fn send_raw(api: &HidApi, options: &Options, words: &[String], no_pad: bool) -> Result<()> {
+    let report = raw_report(words, no_pad)?;
+    let devices = open_devices(api, options)?;
+    for device in &devices {
+        device.write_report(&report)?;
+    }
+    
+    let hex: Vec<String> = report.iter().map(|byte| format!("{:02x}", byte)).collect();
+    println!("Sent {} byte(s) to {} device(s): {}", report.len(), devices.len(), hex.join(" "));
+    Ok(())
+}
+
+/// `risemode-driver identify`: blinks 88.8 on one display, leaving the
+/// others alone, then blanks it.
+fn identify(api: &HidApi, options: &Options, display: &str, seconds: u64) -> Result<()> {
+    let named = options.devices.iter().chain(&options.nicknames).find(|spec| spec.name.as_deref() == Some(display));
+    let selector = match named {
+        Some(spec) => spec.selector.clone(),
+        None => display
+            .parse::<DeviceSelector>()
+            .map_err(|e| anyhow::anyhow!("{} is not a display name or selector: {}", display, e))?,
+    };
+    
+    let info = device::find_displays(api, options.vendor_id(), options.product_id())
+        .into_iter()
+        .find(|info| selector.matches(info))
+        .with_context(|| format!("No connected display matches {}", selector))?;
+    let device = open_device(api, info, Some(display.to_string()))?;
+    
+    println!("Blinking {} ({}) for {} s", display, device.path(), seconds);
+    let lit = Frame::temperature(88.8)?;
+    let blank = Frame::new();
+    let started = Instant::now();
+    let mut on = true;
+    while started.elapsed() < Duration::from_secs(seconds) {
+        device.send(if on { &lit } else { &blank })?;
+        on = !on;
+        thread::sleep(Duration::from_millis(250));
+    }
+    
+    device.send(&blank)
+}
+
+fn probe(api: &HidApi, options: &Options) -> Result<()> {
+    let mut healthy = true;
+    
+    println!("Display (VID: 0x{:04x}, PID: 0x{:04x}):", options.vendor_id(), options.product_id());
+    match open_devices(api, options) {
+        Ok(devices) => {
+            for device in &devices {
+                match device.read_report(PROBE_READ_TIMEOUT) {
+                    Ok(Some(report)) => println!("  {}: OK, sends reports: {}", device.name(), report),
+                    Ok(None) => println!("  {}: OK", device.name()),
+                    Err(e) => println!("  {}: OK, but reading back failed: {:#}", device.name(), e),
                 }
             }
-            Err(e) => {
-                eprintln!("Warning: Failed to read temperature: {}", e);
-            }
         }
+        Err(e) => {
+            println!("  {:#}", e);
+            healthy = false;
+        }
+    }
+    
+    println!("Temperature sensors:");
+    let mut sources: Vec<Box<dyn TemperatureSource>> = vec![
+        Box::new(hwmon_source(options)),
+        Box::new(ThermalZoneSource),
+        Box::new(AmdGpuSource::new(options.gpu_temp.into())),
+    ];
+    #[cfg(windows)]
+    {
+        sources.push(Box::new(sensors::LibreHardwareMonitorSource::new()));
+        sources.push(Box::new(sensors::AcpiThermalSource::new()));
+    }
+    #[cfg(target_os = "macos")]
+    sources.push(Box::new(sensors::SmcSource::new()));
+    for source in &mut sources {
+        match source.read() {
+            Ok(temp) => println!("  {}: {:.1}°C", source.name(), temp),
+            Err(e) => println!("  {}: unavailable ({})", source.name(), e),
+        }
+    }
+    
+    let mut selected = temperature_source(options);
+    if let Err(e) = selected.read() {
+        println!("  Selected sensor ({:?}) has no reading: {}", options.sensor, e);
+        healthy = false;
+        
+        if let Some(hypervisor) = sensors::detect_hypervisor() {
+            println!("  Running inside a virtual machine ({}), host sensors are not visible", hypervisor);
+        }
+    }
+    for (source, health) in selected.health() {
+        println!("  {} (selected sensor): {}", source, health);
+    }
+    
+    if !healthy {
+        anyhow::bail!("Probe found problems, see above");
+    }
+    
+    println!("Everything looks good");
+    Ok(())
+}
+
+/// `risemode-driver sensors`: every hwmon temperature, named like the
+/// `sensors` command names it.
+fn list_sensors() -> Result<()> {
+    let config = SensorsConfig::load();
+    let mut chips: Vec<_> = std::fs::read_dir("/sys/class/hwmon")
+        .context("Failed to read /sys/class/hwmon")?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    chips.sort();
+    
+    for hwmon in &chips {
+        let Some(chip) = sensors::chip_name(hwmon) else {
+            continue;
+        };
+        let device = sensors::hwmon_device_id(hwmon).map(|id| format!(", device {}", id)).unwrap_or_default();
+        println!("{} ({}{})", chip, hwmon.display(), device);
         
-        thread::sleep(Duration::from_millis(UPDATE_INTERVAL_MS));
+        for feature in sensors::temp_features(hwmon) {
+            if config.is_ignored(&chip, &feature) {
+                continue;
+            }
+            
+            let driver_label = std::fs::read_to_string(hwmon.join(format!("{}_label", feature)));
+            let label = match (config.label(&chip, &feature), driver_label) {
+                (Some(label), _) => format!("{} ({})", label, feature),
+                (None, Ok(label)) => format!("{} ({})", label.trim(), feature),
+                (None, Err(_)) => feature.clone(),
+            };
+            
+            match std::fs::read_to_string(hwmon.join(format!("{}_input", feature)))
+                .ok()
+                .and_then(|value| value.trim().parse::<i32>().ok())
+            {
+                Some(millidegrees) => println!("  {}: {:.1}°C", label, millidegrees as f32 / 1000.0),
+                None => println!("  {}: unavailable", label),
+            }
+        }
+    }
+    
+    if chips.is_empty() {
+        println!("No hwmon chips found");
     }
+    Ok(())
 }
 
-fn read_cpu_temperature() -> Result<f32> {
-    // Try to read from hwmon (most common on Linux)
-    if let Ok(temp) = read_hwmon_temperature() {
-        return Ok(temp);
+fn power_cycle(api: &mut HidApi, options: &Options) -> Result<()> {
+    let usb_dirs = recovery::usb_devices(api, options);
+    
+    if usb_dirs.is_empty() && !options.devices.is_empty() {
+        anyhow::bail!("None of the displays given with --device are connected");
+    }
+    if usb_dirs.is_empty() {
+        anyhow::bail!("No matching HID devices found");
     }
     
-    // Try to read from thermal_zone (alternative method)
-    if let Ok(temp) = read_thermal_zone_temperature() {
-        return Ok(temp);
+    for usb_dir in &usb_dirs {
+        println!("Power-cycling {}", usb_dir.display());
+        usb::power_cycle(usb_dir)?;
     }
     
-    anyhow::bail!("Could not read CPU temperature from any source")
+    // Wait for the kernel to re-enumerate the display and bind hidraw again
+    thread::sleep(Duration::from_secs(2));
+    api.refresh_devices().context("Failed to refresh the HID device list")?;
+    
+    let found = device::find_devices(api, options.vendor_id(), options.product_id())
+        .filter(|info| selected(options, info).is_some())
+        .count();
+    if found == 0 {
+        anyhow::bail!("The display did not come back after the power cycle");
+    }
+    
+    println!("Display is back ({} HID interface(s))", found);
+    Ok(())
 }
 
-fn read_hwmon_temperature() -> Result<f32> {
-    // Search for CPU temperature in /sys/class/hwmon/
-    let hwmon_path = "/sys/class/hwmon";
+/// How long a tick took. The monotonic clock counts a stopped process but
+/// (on Linux) not a suspended machine, so the wall clock is checked as well.
+fn tick_gap(started: Instant, started_wall: SystemTime) -> Duration {
+    let wall = SystemTime::now().duration_since(started_wall).unwrap_or_default();
+    started.elapsed().max(wall)
+}
+
+/// Disables USB autosuspend for a freshly opened display, unless told not to.
+fn prepare_device(device: &RisemodeDevice, options: &Options) {
+    if options.keep_autosuspend {
+        return;
+    }
     
-    if let Ok(entries) = fs::read_dir(hwmon_path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            
-            // Check if this is a CPU temperature sensor
-            if let Ok(name) = fs::read_to_string(path.join("name")) {
-                let name = name.trim();
-                
-                // Look for common CPU temperature sensor names
-                if name.contains("coretemp") || name.contains("k10temp") || 
-                   name.contains("zenpower") || name.contains("cpu") {
-                    
-                    // Try to read temp1_input (package temperature)
-                    if let Ok(temp_str) = fs::read_to_string(path.join("temp1_input")) {
-                        if let Ok(temp_millidegrees) = temp_str.trim().parse::<i32>() {
-                            return Ok(temp_millidegrees as f32 / 1000.0);
-                        }
-                    }
+    match usb::disable_autosuspend(device.path()) {
+        Ok(true) => info!("Disabled USB autosuspend for {}", device.name()),
+        Ok(false) => {}
+        Err(e) => warn!("Could not disable USB autosuspend: {:#}", e),
+    }
+}
+
+/// Opens displays that appeared since `devices` was opened, leaving the
+/// connected ones alone.
+fn attach_new_devices(api: &mut HidApi, options: &Options, devices: &mut Vec<RisemodeDevice>) {
+    recovery::refresh(api);
+    
+    for info in device::find_displays(api, options.vendor_id(), options.product_id()) {
+        let path = info.path().to_string_lossy();
+        if devices.iter().any(|device| device.path() == path) {
+            continue;
+        }
+        let Some(name) = selected(options, info) else {
+            continue;
+        };
+        
+        match open_device(api, info, name) {
+            Ok(device) => {
+                info!("Attached new display {}", device.name());
+                prepare_device(&device, options);
+                devices.push(device);
+            }
+            Err(e) => warn!("Could not attach {}: {:#}", path, e),
+        }
+    }
+}
+
+/// Publishes what a route just showed, keyed by its display name and metric
+/// so each gets its own Home Assistant sensor.
+fn publish_reading(mqtt: &mut Mqtt, route_name: Option<&str>, channel: &Channel, shown: f32) {
+    let metric = channel.metric().name();
+    let (key, name) = match route_name {
+        Some(route_name) => {
+            (mqtt::slug(&format!("{}_{}", route_name, metric)), format!("{} {}", route_name, channel.label))
+        }
+        None => (mqtt::slug(&metric), channel.label.to_string()),
+    };
+    mqtt.reading(&key, &name, shown, channel.unit);
+}
+
+/// Drains what the displays sent since the last tick. Nothing in them is
+/// understood yet, so reports are only counted and logged: the first from each
+/// display at info, the rest at debug when they change.
+fn read_back(devices: &[RisemodeDevice], last_reports: &mut HashMap<String, InputReport>, usage: &mut Usage) {
+    for device in devices {
+        for _ in 0..MAX_READS_PER_TICK {
+            let report = match device.read_report(Duration::ZERO) {
+                Ok(Some(report)) => report,
+                Ok(None) => break,
+                Err(e) => {
+                    // A display that went away shows up as a write failure on the next tick
+                    debug!("{:#}", e);
+                    break;
                 }
+            };
+            
+            usage.counters.reports_received += 1;
+            match last_reports.get(device.path()) {
+                None => info!("{} sends reports (--log-level debug shows every change): {}", device.name(), report),
+                Some(last) if *last != report => debug!("{} sent a report: {}", device.name(), report),
+                Some(_) => {}
             }
+            last_reports.insert(device.path().to_string(), report);
         }
     }
-    
-    anyhow::bail!("No hwmon temperature sensors found")
 }
 
-fn read_thermal_zone_temperature() -> Result<f32> {
-    // Try reading from thermal zones
-    let thermal_path = "/sys/class/thermal";
+fn run_temperature_sender(
+    api: &mut HidApi,
+    options: &Options,
+    recovery: &mut Recovery,
+    usage: &mut Usage,
+    hotplug: Option<&HotplugMonitor>,
+    integrations: &mut Integrations,
+) -> Result<Exit> {
+    let mut state = State::Connecting;
+    integrations.state("connecting");
+    
+    // Find and connect to HID devices with matching vendor/product ID
+    info!(
+        "[{:?}] Searching for HID devices (VID: 0x{:04x}, PID: 0x{:04x})...",
+        state,
+        options.vendor_id(),
+        options.product_id()
+    );
+    
+    let mut devices = open_devices(api, options)?;
+    for device in &devices {
+        prepare_device(device, options);
+    }
+    
+    info!("Connected to {} device(s)", devices.len());
+    usage.counters.connects += 1;
+    if let Some(notifier) = &integrations.notifier {
+        notifier.ready();
+    }
+    integrations.status(&format!("Connected to {} display(s), waiting for a reading", devices.len()));
+    state = State::WaitingForSensor;
+    integrations.state("waiting_for_sensor");
+    info!("[{:?}] Starting temperature monitoring...", state);
     
-    if let Ok(entries) = fs::read_dir(thermal_path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            let name = entry.file_name();
+    let interval = Duration::from_millis(options.interval);
+    let mut next_tick = Instant::now();
+    let gap_threshold = (interval * GAP_INTERVALS).max(MIN_GAP);
+    let mut routes = routes(options)?;
+    if let Some(dbus) = &integrations.dbus {
+        // Keep a metric chosen over D-Bus across reconnects
+        let metrics = dbus.metrics();
+        dbus.take_metric_change();
+        if metrics != options.metric
+            && let Some(route) = Route::new(None, &metrics, options)
+        {
+            routes[0] = route;
+        }
+    }
+    let mut vm_hint_shown = false;
+    let mut was_paused = false;
+    
+    // Continuously read CPU temperature and send to devices
+    let mut attach_at = None;
+    let mut blink = false;
+    let mut last_reports = HashMap::new();
+    
+    loop {
+        if signals::stop_requested() {
+            if let Some(frame) = options.parting_frame.frame(true) {
+                for device in &devices {
+                    if let Err(e) = device.send(&frame) {
+                        warn!("Could not send the parting frame to {}: {:#}", device.name(), e);
+                    }
+                }
+            }
+            return Ok(Exit::Stop);
+        }
+        if signals::take_reload() {
+            return Ok(Exit::Reload);
+        }
+        
+        let tick_started = Instant::now();
+        let tick_started_wall = SystemTime::now();
+        
+        // Pick up displays plugged in while we are running; removed ones show
+        // up as write failures
+        while let Some(event) = hotplug.and_then(|hotplug| hotplug.wait(Duration::ZERO).ok().flatten()) {
+            if event == HotplugEvent::Added {
+                attach_at = Some(Instant::now() + recovery::HOTPLUG_SETTLE);
+            }
+        }
+        if attach_at.is_some_and(|at| Instant::now() >= at) {
+            attach_at = None;
+            attach_new_devices(api, options, &mut devices);
+        }
+        
+        if let Some(metrics) = integrations.dbus.as_ref().and_then(DbusService::take_metric_change)
+            && let Some(route) = Route::new(None, &metrics, options)
+        {
+            info!("Showing {:?} on the default displays, as asked over D-Bus", metrics);
+            routes[0] = route;
+            if let Some(textfile) = &mut integrations.textfile {
+                textfile.forget("");
+            }
+        }
+        let paused = integrations.is_paused();
+        if paused != was_paused {
+            was_paused = paused;
+            if paused {
+                info!("Paused over D-Bus, the display keeps its last frame");
+                integrations.state("paused");
+                integrations.status("Paused");
+            } else {
+                info!("Resumed over D-Bus");
+                integrations.state(if state == State::Streaming { "streaming" } else { "waiting_for_sensor" });
+            }
+        }
+        
+        let mut status = Vec::new();
+        blink = !blink;
+        for index in 0..routes.len() {
+            let targets: Vec<&RisemodeDevice> =
+                devices.iter().filter(|device| routes[index].serves(device, &routes)).collect();
+            if targets.is_empty()
+                && let Some(textfile) = &mut integrations.textfile
+            {
+                // Its displays are gone, so are their readings
+                textfile.forget(routes[index].name.as_deref().unwrap_or_default());
+            }
+            if targets.is_empty() || paused {
+                continue;
+            }
+            
+            let route = &mut routes[index];
+            let channel = route.rotation.current(tick_started);
+            let reading = channel.read(options);
             
-            if name.to_string_lossy().starts_with("thermal_zone") {
-                // Check if this is a CPU thermal zone
-                if let Ok(zone_type) = fs::read_to_string(path.join("type")) {
-                    let zone_type = zone_type.trim();
+            match reading {
+                Ok(None) => {
+                    // Rejected before any valid reading existed, nothing to fall back on
+                }
+                Ok(Some(temp)) => {
+                    if state == State::WaitingForSensor {
+                        state = State::Streaming;
+                        info!("[{:?}] First valid reading, sending to display", state);
+                        integrations.state("streaming");
+                    }
+                    
+                    let (shown, frame) =
+                        encode(channel, route.idle_blanker.as_mut(), route.ramp.as_mut(), temp, options);
+                    let prefix = route.name.as_deref().map(|name| format!("[{}] ", name)).unwrap_or_default();
+                    // Once per tick, too often for the journal at the default level
+                    debug!(
+                        "{}{}: {:.1}{} (sending {})",
+                        prefix, channel.label, shown, channel.unit, frame
+                    );
+                    
+                    // Send to the HID devices on this route
+                    for device in &targets {
+                        if let Err(e) = device.send(&frame) {
+                            recovery.write_failed(device.path());
+                            return Err(e);
+                        }
+                    }
+                    recovery.frames_written();
+                    usage.counters.frames_sent += targets.len() as u64;
+                    if let Some(mqtt) = &mut integrations.mqtt {
+                        publish_reading(mqtt, route.name.as_deref(), channel, shown);
+                    }
+                    if let Some(textfile) = &mut integrations.textfile {
+                        // In °C, not --unit
+                        let display = route.name.as_deref().unwrap_or_default();
+                        textfile.reading(display, &channel.metric().name(), temp, channel.is_temperature());
+                    }
+                    status.push(format!("{}{}: {:.1}{}", prefix, channel.label, shown, channel.unit));
+                }
+                Err(e) if state == State::WaitingForSensor => {
+                    usage.counters.sensor_failures += 1;
+                    warn!("[{:?}] No temperature yet, nothing sent: {}", state, e);
+                    
+                    if !vm_hint_shown && let Some(hypervisor) = sensors::detect_hypervisor() {
+                        warn!(
+                            "Running inside a virtual machine ({}). The host's CPU sensors are not visible to the \
+                             guest, so run the driver on the host with the cooler attached there instead.",
+                            hypervisor
+                        );
+                        vm_hint_shown = true;
+                    }
+                }
+                Err(e) => {
+                    usage.counters.sensor_failures += 1;
+                    warn!("Failed to read temperature: {}", e);
                     
-                    if zone_type.contains("cpu") || zone_type.contains("x86_pkg_temp") {
-                        if let Ok(temp_str) = fs::read_to_string(path.join("temp")) {
-                            if let Ok(temp_millidegrees) = temp_str.trim().parse::<i32>() {
-                                return Ok(temp_millidegrees as f32 / 1000.0);
+                    if let Some(frame) = options.parting_frame.frame(blink) {
+                        for device in &targets {
+                            if let Err(e) = device.send(&frame) {
+                                recovery.write_failed(device.path());
+                                return Err(e);
                             }
                         }
                     }
                 }
             }
+            
+            for (source, health) in channel.source_health() {
+                if health.is_degraded() {
+                    status.push(format!("{} {}", source, health));
+                }
+            }
+        }
+        
+        read_back(&devices, &mut last_reports, usage);
+        if let Some(mqtt) = &mut integrations.mqtt {
+            mqtt.keep_alive();
+        }
+        
+        if !status.is_empty() {
+            integrations.status(&status.join(", "));
+        }
+        if let Some(notifier) = &integrations.notifier {
+            notifier.watchdog();
         }
+        integrations.write_metrics(&usage.counters, devices.len());
+        usage.maybe_save();
+        
+        // Sleep until an absolute deadline so the time spent reading sensors and
+        // writing frames doesn't accumulate as drift
+        next_tick += interval;
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        }
+        
+        let gap = tick_gap(tick_started, tick_started_wall);
+        if gap >= gap_threshold {
+            // Suspended or stopped: the readings in the filters are stale, and
+            // the next one goes out now instead of catching up on missed ticks
+            info!("Resumed after {:.1} s without updates, resynchronizing", gap.as_secs_f32());
+            for route in &mut routes {
+                route.rotation.reset_filters();
+            }
+            next_tick = Instant::now();
+        } else if next_tick <= now {
+            // Already past the deadline: start over from now rather than bursting
+            next_tick = now;
+        } else {
+            let late = next_tick.elapsed();
+            if late > interval / 2 {
+                warn!("Update ran {} ms late", late.as_millis());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SECOND: Duration = Duration::from_secs(1);
+    
+    #[test]
+    fn idle_blanker_blanks_after_the_delay() {
+        let start = Instant::now();
+        let mut blanker = IdleBlanker::new(40.0, 10 * SECOND);
+        
+        assert!(!blanker.update(35.0, start));
+        assert!(!blanker.update(35.0, start + 9 * SECOND));
+        assert!(blanker.update(35.0, start + 10 * SECOND));
+        assert!(blanker.update(39.9, start + 60 * SECOND));
+    }
+    
+    #[test]
+    fn idle_blanker_wakes_on_a_reading_above_the_threshold() {
+        let start = Instant::now();
+        let mut blanker = IdleBlanker::new(40.0, 10 * SECOND);
+        blanker.update(35.0, start);
+        assert!(blanker.update(35.0, start + 10 * SECOND));
+        
+        assert!(!blanker.update(40.0, start + 11 * SECOND));
+        // Idle time starts over after waking
+        assert!(!blanker.update(35.0, start + 12 * SECOND));
+        assert!(!blanker.update(35.0, start + 21 * SECOND));
+        assert!(blanker.update(35.0, start + 22 * SECOND));
     }
     
-    anyhow::bail!("No thermal zone temperature found")
+    #[test]
+    fn idle_blanker_needs_uninterrupted_idle_time() {
+        let start = Instant::now();
+        let mut blanker = IdleBlanker::new(40.0, 10 * SECOND);
+        blanker.update(35.0, start);
+        blanker.update(45.0, start + 5 * SECOND);
+        
+        assert!(!blanker.update(35.0, start + 10 * SECOND));
+        assert!(!blanker.update(35.0, start + 19 * SECOND));
+        assert!(blanker.update(35.0, start + 20 * SECOND));
+    }
+    
+    #[test]
+    fn ramp_eases_in_from_the_first_value() {
+        let start = Instant::now();
+        let mut ramp = Ramp::new(10 * SECOND);
+        
+        // The ramp starts with the first value
+        assert_eq!(ramp.apply(40.0, start), 0.0);
+        assert_eq!(ramp.apply(40.0, start + 5 * SECOND), 20.0);
+        
+        let steps: Vec<f32> = (0..=10).map(|secs| ramp.apply(40.0, start + secs * SECOND)).collect();
+        assert!(steps.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", steps);
+        assert!(steps[1] < 40.0 * 0.1 && steps[9] > 40.0 * 0.9, "{:?}", steps);
+        assert_eq!(steps[10], 40.0);
+    }
+    
+    #[test]
+    fn ramp_passes_values_through_once_done() {
+        let start = Instant::now();
+        let mut ramp = Ramp::new(10 * SECOND);
+        ramp.apply(40.0, start);
+        
+        for (secs, value) in [(10, 45.3), (11, 0.0), (3600, 99.9)] {
+            assert_eq!(ramp.apply(value, start + secs * SECOND), value);
+        }
+    }
+    
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+    
+    #[test]
+    fn hex_words_parse_with_or_without_prefixes() {
+        assert_eq!(parse_hex(&words(&["3a", "01", "ff"])).unwrap(), [0x3a, 0x01, 0xff]);
+        assert_eq!(parse_hex(&words(&["3a0100"])).unwrap(), [0x3a, 0x01, 0x00]);
+        assert_eq!(parse_hex(&words(&["0x3a", "0XFF", "0x0100"])).unwrap(), [0x3a, 0xff, 0x01, 0x00]);
+        assert_eq!(parse_hex(&words(&["3a 01", " 00 "])).unwrap(), [0x3a, 0x01, 0x00]);
+    }
+    
+    #[test]
+    fn malformed_hex_words_are_rejected() {
+        for word in ["3", "3a0", "0x", "0x0x3a", "zz", "+1", "3a:01", "3a,01", "3a-01", "é1"] {
+            assert!(parse_hex(&words(&["01", word])).is_err(), "{:?}", word);
+        }
+        assert!(parse_hex(&words(&["", " "])).is_err());
+    }
+    
+    #[test]
+    fn raw_reports_are_padded_unless_asked_not_to() {
+        let report = raw_report(&words(&["3a", "01"]), false).unwrap();
+        assert_eq!(report.len(), FRAME_LEN);
+        assert_eq!(report[..3], [0x3a, 0x01, 0x00]);
+        assert_eq!(raw_report(&words(&["3a", "01"]), true).unwrap(), [0x3a, 0x01]);
+        
+        let full = "ab".repeat(FRAME_LEN);
+        assert_eq!(raw_report(&words(&[&full]), false).unwrap(), [0xab; FRAME_LEN]);
+        let too_long = "ab".repeat(FRAME_LEN + 1);
+        assert!(raw_report(&words(&[&too_long]), false).is_err());
+        assert_eq!(raw_report(&words(&[&too_long]), true).unwrap().len(), FRAME_LEN + 1);
+    }
 }
@@ -0,0 +1,250 @@
+//! What the display shows: a single metric, or several taking turns.
+
+use anyhow::Result;
+use risemode_driver::filter::{ReadingValidator, Smoother, SpikeFilter};
+use risemode_driver::{AmdGpuSource, CpuLoadSource, MemoryUsageSource, SourceHealth, TemperatureSource};
+use std::time::{Duration, Instant};
+
+use crate::cli::{Metric, Options, SensorKind, Smoothing};
+
+const MAX_DISPLAYED_PERCENT: f32 = 99.9; // The display has two integer digits
+
+/// One metric with its own source and filters, so switching between
+/// metrics never looks like a temperature jump.
+pub struct Channel {
+    metric: Metric,
+    source: Box<dyn TemperatureSource>,
+    validator: ReadingValidator,
+    spike_filter: SpikeFilter,
+    smoother: Option<Smoother>,
+    pub label: &'static str,
+    pub unit: &'static str,
+}
+
+impl Channel {
+    fn new(metric: Metric, options: &Options) -> Self {
+        let degrees = options.unit.symbol();
+        let (source, label, unit): (Box<dyn TemperatureSource>, _, _) = match metric {
+            Metric::Temperature => (crate::temperature_source(options), sensor_label(options.sensor), degrees),
+            Metric::GpuTemperature => (Box::new(AmdGpuSource::new(options.gpu_temp.into())), "GPU", degrees),
+            Metric::Load => (Box::new(CpuLoadSource::new()), "Load", "%"),
+            Metric::Memory => (Box::new(MemoryUsageSource), "RAM", "%"),
+        };
+        
+        Self {
+            metric,
+            source,
+            validator: validator(options),
+            spike_filter: SpikeFilter::new(),
+            smoother: smoother(options),
+            label,
+            unit,
+        }
+    }
+    
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+    
+    /// Health of the sources behind this channel; see [`TemperatureSource::health`].
+    pub fn source_health(&self) -> Vec<(&str, &SourceHealth)> {
+        self.source.health()
+    }
+    
+    /// Whether readings are temperatures (and so meaningful to the idle blanker).
+    pub fn is_temperature(&self) -> bool {
+        matches!(self.metric, Metric::Temperature | Metric::GpuTemperature)
+    }
+    
+    /// Takes a reading and runs it through the sanity checks. `Ok(None)`
+    /// means it was rejected before any valid reading existed. Temperatures
+    /// are in °C; see [`Channel::displayed`].
+    pub fn read(&mut self, options: &Options) -> Result<Option<f32>> {
+        let reading = self.source.read()?;
+        
+        let value = if self.is_temperature() {
+            let Some(temp) = self.validator.check(reading) else {
+                return Ok(None);
+            };
+            if options.no_spike_filter { temp } else { self.spike_filter.apply(temp) }
+        } else {
+            // Percentages legitimately sit at 0 and jump further than any temperature
+            reading.min(MAX_DISPLAYED_PERCENT)
+        };
+        
+        Ok(Some(match &mut self.smoother {
+            Some(smoother) => smoother.apply(value),
+            None => value,
+        }))
+    }
+    
+    /// Converts a reading from [`Channel::read`] to the value shown, in `--unit`.
+    pub fn displayed(&self, value: f32, options: &Options) -> f32 {
+        if self.is_temperature() { options.unit.convert(value) } else { value }
+    }
+    
+    fn reset_filters(&mut self) {
+        self.validator.reset();
+        self.spike_filter = SpikeFilter::new();
+        if let Some(smoother) = &mut self.smoother {
+            smoother.reset();
+        }
+    }
+}
+
+fn validator(options: &Options) -> ReadingValidator {
+    ReadingValidator::with_bounds(options.min_temp, options.max_temp, options.max_jump)
+}
+
+fn smoother(options: &Options) -> Option<Smoother> {
+    let window = options.smoothing_window as usize;
+    
+    match options.smoothing {
+        Smoothing::None => None,
+        Smoothing::Ema => Some(Smoother::ema(window)),
+        Smoothing::Average => Some(Smoother::moving_average(window)),
+    }
+}
+
+fn sensor_label(sensor: SensorKind) -> &'static str {
+    match sensor {
+        SensorKind::Auto | SensorKind::Hwmon | SensorKind::Thermal => "CPU",
+        SensorKind::Amdgpu => "GPU",
+        SensorKind::Hottest => "Hottest",
+    }
+}
+
+/// Cycles through a list of metrics every `--rotate-every` seconds.
+/// Holds at least one channel.
+pub struct Rotation {
+    channels: Vec<Channel>,
+    active: usize,
+    period: Duration,
+    switched_at: Instant,
+}
+
+impl Rotation {
+    /// `None` if `metrics` is empty, as there would be nothing to show.
+    pub fn new(metrics: &[Metric], options: &Options) -> Option<Self> {
+        if metrics.is_empty() {
+            return None;
+        }
+        let channels = metrics.iter().map(|&metric| Channel::new(metric, options)).collect();
+        
+        Some(Self {
+            channels,
+            active: 0,
+            period: Duration::from_secs(options.rotate_every),
+            switched_at: Instant::now(),
+        })
+    }
+    
+    /// Forgets the readings held by every channel's filters, e.g. after the
+    /// machine was suspended.
+    pub fn reset_filters(&mut self) {
+        for channel in &mut self.channels {
+            channel.reset_filters();
+        }
+    }
+    
+    /// The channel to show on the tick starting at `now`, moving on once the
+    /// current one has had its turn. A channel coming back starts with empty
+    /// filters, so what it held from its last turn doesn't leak onto the display.
+    pub fn current(&mut self, now: Instant) -> &mut Channel {
+        if self.channels.len() > 1 && now.duration_since(self.switched_at) >= self.period {
+            self.active = (self.active + 1) % self.channels.len();
+            self.switched_at = now;
+            self.channels[self.active].reset_filters();
+        }
+        
+        &mut self.channels[self.active]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+    use std::collections::VecDeque;
+    
+    const PERIOD: Duration = Duration::from_secs(10);
+    
+    /// Returns the given readings in turn.
+    struct Scripted(VecDeque<f32>);
+    
+    impl TemperatureSource for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+        
+        fn read(&mut self) -> Result<f32> {
+            self.0.pop_front().ok_or_else(|| anyhow::anyhow!("out of readings"))
+        }
+    }
+    
+    fn options() -> Options {
+        Cli::parse_from(["risemode-driver"]).options
+    }
+    
+    fn channel(metric: Metric, readings: &[f32]) -> Channel {
+        Channel {
+            metric,
+            source: Box::new(Scripted(readings.iter().copied().collect())),
+            validator: ReadingValidator::new(),
+            spike_filter: SpikeFilter::new(),
+            smoother: Some(Smoother::moving_average(3)),
+            label: "",
+            unit: "",
+        }
+    }
+    
+    fn rotation(channels: Vec<Channel>, start: Instant) -> Rotation {
+        Rotation { channels, active: 0, period: PERIOD, switched_at: start }
+    }
+    
+    #[test]
+    fn channels_take_turns() {
+        let start = Instant::now();
+        let mut rotation = rotation(vec![channel(Metric::Temperature, &[]), channel(Metric::Load, &[])], start);
+        
+        assert_eq!(rotation.current(start).metric(), Metric::Temperature);
+        assert_eq!(rotation.current(start + PERIOD / 2).metric(), Metric::Temperature);
+        assert_eq!(rotation.current(start + PERIOD).metric(), Metric::Load);
+        assert_eq!(rotation.current(start + PERIOD * 3 / 2).metric(), Metric::Load);
+        assert_eq!(rotation.current(start + PERIOD * 2).metric(), Metric::Temperature);
+    }
+    
+    #[test]
+    fn a_single_channel_keeps_its_filters() {
+        let options = options();
+        let start = Instant::now();
+        let mut rotation = rotation(vec![channel(Metric::Load, &[30.0, 60.0])], start);
+        
+        assert_eq!(rotation.current(start).read(&options).unwrap(), Some(30.0));
+        assert_eq!(rotation.current(start + PERIOD * 5).read(&options).unwrap(), Some(45.0));
+    }
+    
+    #[test]
+    fn a_channel_coming_back_starts_with_empty_filters() {
+        let options = options();
+        let start = Instant::now();
+        let mut rotation = rotation(
+            vec![channel(Metric::Temperature, &[40.0, 41.0, 90.0]), channel(Metric::Load, &[20.0])],
+            start,
+        );
+        
+        assert_eq!(rotation.current(start).read(&options).unwrap(), Some(40.0));
+        assert_eq!(rotation.current(start).read(&options).unwrap(), Some(40.5));
+        assert_eq!(rotation.current(start + PERIOD).read(&options).unwrap(), Some(20.0));
+        
+        // Neither the average nor the jump check remember 40-41°C
+        assert_eq!(rotation.current(start + PERIOD * 2).read(&options).unwrap(), Some(90.0));
+    }
+    
+    #[test]
+    fn an_empty_metric_list_is_refused() {
+        assert!(Rotation::new(&[], &options()).is_none());
+        assert!(Rotation::new(&[Metric::Load], &options()).is_some());
+    }
+}
@@ -0,0 +1,669 @@
+//! Publishing of the readings to an MQTT broker, with Home Assistant discovery.
+//!
+//! This is a minimal MQTT 3.1.1 client, enough for a broker on the local
+//! network: plain TCP and QoS 0 publishes only, with nothing read back after
+//! the CONNACK. A broker that is down or can't be resolved never stops the
+//! display: connects run on a worker thread, and publishes are dropped until
+//! one succeeds. A reconnect is tried every [`RETRY_INTERVAL`] and looks the
+//! broker up again.
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::env;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::cli::Options;
+
+const DEFAULT_PORT: u16 = 1883;
+const PASSWORD_VAR: &str = "RISEMODE_MQTT_PASSWORD";
+const TIMEOUT: Duration = Duration::from_secs(2);
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+const MIN_KEEP_ALIVE_SECS: u64 = 60;
+
+/// A connection to the broker, reopened as needed.
+pub struct Mqtt {
+    host: String,
+    port: u16,
+    /// `host:port` for messages, with IPv6 addresses in brackets
+    broker: String,
+    client_id: String,
+    username: Option<String>,
+    password: Option<String>,
+    keep_alive: u16,
+    /// Topics are `<base>/availability`, `<base>/status` and `<base>/<key>`
+    base: String,
+    discovery_prefix: String,
+    node_id: String,
+    host_name: String,
+    stream: Option<TcpStream>,
+    /// The connect running on a worker thread, if any
+    connecting: Option<JoinHandle<Result<TcpStream>>>,
+    last_sent: Instant,
+    retry_at: Option<Instant>,
+    /// Keys whose discovery config went out on the current connection
+    announced: HashSet<String>,
+    /// The last status, published again on each new connection
+    status: Option<String>,
+    status_sent: bool,
+}
+
+impl Mqtt {
+    /// The client for the `--mqtt` broker, or `None` without it. Only a
+    /// malformed `--mqtt` fails: the broker is looked up on each connect.
+    pub fn new(options: &Options) -> Result<Option<Self>> {
+        let Some(broker) = &options.mqtt else {
+            return Ok(None);
+        };
+        
+        let (host, port) = parse_broker(broker).with_context(|| format!("Invalid --mqtt {}", broker))?;
+        let broker = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+        
+        let host_name = sysinfo::System::host_name().unwrap_or_else(|| "localhost".to_string());
+        let node = slug(&host_name);
+        let keep_alive = (options.interval * 3 / 1000).max(MIN_KEEP_ALIVE_SECS).min(u16::MAX as u64) as u16;
+        
+        Ok(Some(Self {
+            host,
+            port,
+            broker,
+            client_id: format!("risemode-driver-{}", node),
+            username: options.mqtt_username.clone(),
+            password: env::var(PASSWORD_VAR).ok(),
+            keep_alive,
+            base: format!("{}/{}", options.mqtt_topic_prefix.trim_end_matches('/'), node),
+            discovery_prefix: options.mqtt_discovery_prefix.trim_end_matches('/').to_string(),
+            node_id: format!("risemode_{}", node),
+            host_name,
+            stream: None,
+            connecting: None,
+            last_sent: Instant::now(),
+            retry_at: None,
+            announced: HashSet::new(),
+            status: None,
+            status_sent: false,
+        }))
+    }
+    
+    /// Publishes what the driver is doing, e.g. `streaming` or `reconnecting`.
+    pub fn status(&mut self, status: &str) {
+        self.status = Some(status.to_string());
+        self.status_sent = false;
+        self.flush_status();
+    }
+    
+    fn flush_status(&mut self) {
+        if !self.ensure_connected() || self.status_sent {
+            return;
+        }
+        let Some(status) = self.status.clone() else {
+            return;
+        };
+        
+        if !self.announced.contains("status") {
+            let config = self.discovery_config("status", "Status", None);
+            self.announce("status", &config);
+        }
+        let topic = format!("{}/status", self.base);
+        self.status_sent = self.publish(&topic, status.as_bytes(), true);
+    }
+    
+    /// Publishes one reading, announcing it to Home Assistant the first time.
+    /// `key` names the topic and must stay the same for the same reading.
+    pub fn reading(&mut self, key: &str, name: &str, value: f32, unit: &str) {
+        if !self.announced.contains(key) && self.ensure_connected() {
+            let config = self.discovery_config(key, name, Some(unit));
+            self.announce(key, &config);
+        }
+        
+        let topic = format!("{}/{}", self.base, key);
+        self.publish(&topic, format!("{:.1}", value).as_bytes(), false);
+    }
+    
+    /// Pings the broker when nothing else went out for half the keep-alive
+    /// time, e.g. while the sensor has no reading, and discards what the
+    /// broker sent back. Call once per tick, also to pick up a finished connect.
+    pub fn keep_alive(&mut self) {
+        if !self.ensure_connected() {
+            return;
+        }
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+        
+        // PINGRESPs pile up otherwise; nothing else is expected for QoS 0
+        let mut buf = [0u8; 64];
+        let mut closed = false;
+        if stream.set_nonblocking(true).is_ok() {
+            loop {
+                match stream.read(&mut buf) {
+                    Ok(0) => {
+                        closed = true;
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(_) => break,
+                }
+            }
+            let _ = stream.set_nonblocking(false);
+        }
+        if closed {
+            warn!("The MQTT broker {} closed the connection", self.broker);
+            self.stream = None;
+            return;
+        }
+        
+        if self.last_sent.elapsed() >= Duration::from_secs(self.keep_alive as u64 / 2) {
+            self.send(&[0xc0, 0x00]);
+        }
+    }
+    
+    /// Marks the driver offline and closes the connection. Does nothing when
+    /// not connected: the broker publishes the will in that case.
+    pub fn disconnect(&mut self) {
+        if self.stream.is_none() {
+            return;
+        }
+        
+        let topic = format!("{}/availability", self.base);
+        self.publish(&topic, b"offline", true);
+        
+        if let Some(mut stream) = self.stream.take() {
+            let _ = stream.write_all(&[0xe0, 0x00]);
+        }
+    }
+    
+    fn announce(&mut self, key: &str, config: &str) {
+        let topic = format!("{}/sensor/{}/{}/config", self.discovery_prefix, self.node_id, key);
+        if self.publish(&topic, config.as_bytes(), true) {
+            self.announced.insert(key.to_string());
+        }
+    }
+    
+    /// The Home Assistant discovery payload of one sensor. Readings without a
+    /// unit are text sensors, shown with the diagnostics.
+    fn discovery_config(&self, key: &str, name: &str, unit: Option<&str>) -> String {
+        let mut fields = vec![
+            format!("\"name\":{}", json_string(name)),
+            format!("\"unique_id\":{}", json_string(&format!("{}_{}", self.node_id, key))),
+            format!("\"state_topic\":{}", json_string(&format!("{}/{}", self.base, key))),
+            format!("\"availability_topic\":{}", json_string(&format!("{}/availability", self.base))),
+        ];
+        match unit {
+            Some(unit) => {
+                fields.push(format!("\"unit_of_measurement\":{}", json_string(unit)));
+                fields.push("\"state_class\":\"measurement\"".to_string());
+                if unit.starts_with('°') {
+                    fields.push("\"device_class\":\"temperature\"".to_string());
+                }
+            }
+            None => fields.push("\"entity_category\":\"diagnostic\"".to_string()),
+        }
+        fields.push(format!(
+            "\"device\":{{\"identifiers\":[{}],\"name\":{},\"manufacturer\":\"Rise Mode\",\
+             \"model\":\"Aura Ice Black display\",\"sw_version\":{}}}",
+            json_string(&self.node_id),
+            json_string(&format!("Rise Mode display ({})", self.host_name)),
+            json_string(env!("CARGO_PKG_VERSION"))
+        ));
+        
+        format!("{{{}}}", fields.join(","))
+    }
+    
+    /// Sends one QoS 0 PUBLISH and returns whether it went out.
+    fn publish(&mut self, topic: &str, payload: &[u8], retain: bool) -> bool {
+        if !self.ensure_connected() {
+            return false;
+        }
+        
+        let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+        put_string(&mut body, topic);
+        body.extend_from_slice(payload);
+        self.send(&packet(0x30 | retain as u8, &body))
+    }
+    
+    /// Writes a whole packet, dropping the connection if that fails.
+    fn send(&mut self, packet: &[u8]) -> bool {
+        let Some(stream) = &mut self.stream else {
+            return false;
+        };
+        if let Err(e) = stream.write_all(packet) {
+            warn!("Lost the connection to the MQTT broker {}: {}", self.broker, e);
+            self.stream = None;
+            self.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+            return false;
+        }
+        
+        self.last_sent = Instant::now();
+        true
+    }
+    
+    /// Whether there is a connection to publish on. Otherwise starts a connect
+    /// when the retry is due, or collects the one that finished since.
+    fn ensure_connected(&mut self) -> bool {
+        if self.stream.is_some() {
+            return true;
+        }
+        
+        let Some(connecting) = self.connecting.take_if(|connecting| connecting.is_finished()) else {
+            if self.connecting.is_none() && self.retry_at.is_none_or(|at| Instant::now() >= at) {
+                let (host, port, packet) = (self.host.clone(), self.port, self.connect_packet());
+                self.connecting = Some(thread::spawn(move || connect(&host, port, &packet)));
+            }
+            return false;
+        };
+        
+        let result = connecting.join().unwrap_or_else(|_| Err(anyhow::anyhow!("The connect thread panicked")));
+        match result {
+            Ok(stream) => {
+                info!("Connected to the MQTT broker {}, publishing under {}/", self.broker, self.base);
+                self.stream = Some(stream);
+                self.retry_at = None;
+                // The broker may have restarted without keeping retained messages
+                self.announced.clear();
+                self.status_sent = false;
+                let topic = format!("{}/availability", self.base);
+                if self.publish(&topic, b"online", true) {
+                    self.flush_status();
+                }
+                self.stream.is_some()
+            }
+            Err(e) => {
+                warn!(
+                    "Could not connect to the MQTT broker {}, retrying in {} s: {:#}",
+                    self.broker,
+                    RETRY_INTERVAL.as_secs(),
+                    e
+                );
+                self.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+                false
+            }
+        }
+    }
+    
+    /// CONNECT with a clean session and a retained `offline` will on the
+    /// availability topic.
+    fn connect_packet(&self) -> Vec<u8> {
+        // Clean session, will with retain
+        let mut flags = 0x02 | 0x04 | 0x20;
+        if self.username.is_some() {
+            flags |= 0x80;
+            if self.password.is_some() {
+                flags |= 0x40;
+            }
+        }
+        
+        let mut body = Vec::new();
+        put_string(&mut body, "MQTT");
+        body.push(4); // Protocol level of 3.1.1
+        body.push(flags);
+        body.extend_from_slice(&self.keep_alive.to_be_bytes());
+        put_string(&mut body, &self.client_id);
+        put_string(&mut body, &format!("{}/availability", self.base));
+        put_string(&mut body, "offline");
+        if let Some(username) = &self.username {
+            put_string(&mut body, username);
+            if let Some(password) = &self.password {
+                put_string(&mut body, password);
+            }
+        }
+        packet(0x10, &body)
+    }
+}
+
+/// Looks `host` up, opens the TCP connection to the first address that
+/// answers and does the CONNECT/CONNACK handshake with `connect_packet`.
+fn connect(host: &str, port: u16, connect_packet: &[u8]) -> Result<TcpStream> {
+    let addresses = (host, port).to_socket_addrs().with_context(|| format!("Failed to resolve {}", host))?;
+    let mut stream = None;
+    let mut error = anyhow::anyhow!("{} has no address", host);
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(e) => error = anyhow::Error::new(e).context(format!("Failed to connect to {}", address)),
+        }
+    }
+    let mut stream = stream.ok_or(error)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.set_nodelay(true)?;
+    
+    stream.write_all(connect_packet).context("Failed to send CONNECT")?;
+    
+    let mut connack = [0u8; 4];
+    stream.read_exact(&mut connack).context("No CONNACK from the broker")?;
+    check_connack(&connack)?;
+    Ok(stream)
+}
+
+/// Splits `--mqtt` into host and port: `host`, `host:port`, an IPv4 or IPv6
+/// address, or `[v6]:port`. Without a port, [`DEFAULT_PORT`] is used.
+fn parse_broker(broker: &str) -> Result<(String, u16)> {
+    if let Ok(address) = broker.parse::<SocketAddr>() {
+        return Ok((address.ip().to_string(), address.port()));
+    }
+    let bare = broker.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')).unwrap_or(broker);
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Ok((ip.to_string(), DEFAULT_PORT));
+    }
+    if broker.contains(['[', ']']) {
+        anyhow::bail!("expected [ADDRESS]:PORT for an IPv6 address");
+    }
+    
+    match broker.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port.parse().with_context(|| format!("{:?} is not a port number", port))?;
+            if host.is_empty() {
+                anyhow::bail!("the host name is empty");
+            }
+            Ok((host.to_string(), port))
+        }
+        None if broker.is_empty() => anyhow::bail!("the host name is empty"),
+        None => Ok((broker.to_string(), DEFAULT_PORT)),
+    }
+}
+
+/// Checks the CONNACK for an accepted connection.
+fn check_connack(connack: &[u8; 4]) -> Result<()> {
+    if connack[0] != 0x20 {
+        anyhow::bail!("Expected a CONNACK, got packet type 0x{:02x}", connack[0]);
+    }
+    match connack[3] {
+        0 => Ok(()),
+        4 | 5 => anyhow::bail!("The broker refused the user name or password"),
+        code => anyhow::bail!("The broker refused the connection (return code {})", code),
+    }
+}
+
+/// A packet with its fixed header: type and flags, then the remaining length.
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Appends a length-prefixed UTF-8 string.
+fn put_string(buf: &mut Vec<u8>, s: &str) {
+    let bytes = &s.as_bytes()[..s.len().min(u16::MAX as usize)];
+    buf.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+/// Lowercase letters, digits and underscores, as Home Assistant expects in IDs.
+pub fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+    
+    fn client(username: Option<&str>, password: Option<&str>) -> Mqtt {
+        Mqtt {
+            host: "127.0.0.1".to_string(),
+            port: DEFAULT_PORT,
+            broker: "127.0.0.1:1883".to_string(),
+            client_id: "risemode-driver-desk".to_string(),
+            username: username.map(str::to_string),
+            password: password.map(str::to_string),
+            keep_alive: 60,
+            base: "risemode/desk".to_string(),
+            discovery_prefix: "homeassistant".to_string(),
+            node_id: "risemode_desk".to_string(),
+            host_name: "desk".to_string(),
+            stream: None,
+            connecting: None,
+            last_sent: Instant::now(),
+            retry_at: None,
+            announced: HashSet::new(),
+            status: None,
+            status_sent: false,
+        }
+    }
+    
+    /// Polls until the connect on the worker thread has finished.
+    fn settle(mqtt: &mut Mqtt) -> bool {
+        let deadline = Instant::now() + 2 * TIMEOUT;
+        while mqtt.connecting.is_some() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+            mqtt.ensure_connected();
+        }
+        mqtt.ensure_connected()
+    }
+    
+    /// CONNECT up to and including the will message.
+    fn connect_prefix(flags: u8) -> Vec<u8> {
+        let mut body = vec![0x00, 0x04];
+        body.extend_from_slice(b"MQTT");
+        body.extend_from_slice(&[0x04, flags, 0x00, 0x3c]);
+        body.extend_from_slice(b"\x00\x14risemode-driver-desk");
+        body.extend_from_slice(b"\x00\x1arisemode/desk/availability");
+        body.extend_from_slice(b"\x00\x07offline");
+        body
+    }
+    
+    #[test]
+    fn remaining_length_is_a_varint() {
+        for (len, encoded) in [
+            (0, &[0x00][..]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (16383, &[0xff, 0x7f]),
+            (16384, &[0x80, 0x80, 0x01]),
+        ] {
+            let packet = packet(0x30, &vec![0xaa; len]);
+            assert_eq!(packet[0], 0x30);
+            assert_eq!(&packet[1..1 + encoded.len()], encoded, "length {}", len);
+            assert_eq!(packet.len(), 1 + encoded.len() + len);
+        }
+    }
+    
+    #[test]
+    fn strings_are_length_prefixed() {
+        let mut buf = Vec::new();
+        put_string(&mut buf, "MQTT");
+        put_string(&mut buf, "");
+        put_string(&mut buf, "°C");
+        assert_eq!(buf, b"\x00\x04MQTT\x00\x00\x00\x03\xc2\xb0C");
+    }
+    
+    #[test]
+    fn connect_without_credentials() {
+        let packet = client(None, None).connect_packet();
+        let body = connect_prefix(0x26);
+        
+        assert_eq!(packet[..2], [0x10, body.len() as u8]);
+        assert_eq!(packet[2..], body[..]);
+    }
+    
+    #[test]
+    fn connect_with_username_and_password() {
+        let packet = client(Some("ha"), Some("secret")).connect_packet();
+        let mut body = connect_prefix(0xe6);
+        body.extend_from_slice(b"\x00\x02ha\x00\x06secret");
+        
+        assert_eq!(packet[..2], [0x10, body.len() as u8]);
+        assert_eq!(packet[2..], body[..]);
+    }
+    
+    #[test]
+    fn connect_with_username_only() {
+        let packet = client(Some("ha"), None).connect_packet();
+        let mut body = connect_prefix(0xa6);
+        body.extend_from_slice(b"\x00\x02ha");
+        
+        assert_eq!(packet[2..], body[..]);
+    }
+    
+    #[test]
+    fn connack_is_checked() {
+        assert!(check_connack(&[0x20, 0x02, 0x00, 0x00]).is_ok());
+        
+        let refused = check_connack(&[0x20, 0x02, 0x00, 0x05]).unwrap_err();
+        assert!(refused.to_string().contains("user name or password"), "{}", refused);
+        let refused = check_connack(&[0x20, 0x02, 0x00, 0x02]).unwrap_err();
+        assert!(refused.to_string().contains("return code 2"), "{}", refused);
+        assert!(check_connack(&[0xd0, 0x00, 0x00, 0x00]).is_err());
+    }
+    
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("CPU"), r#""CPU""#);
+        assert_eq!(json_string("a \"b\" \\ c"), r#""a \"b\" \\ c""#);
+        assert_eq!(json_string("tab\there\n"), r#""tab\u0009here\u000a""#);
+        assert_eq!(json_string("°C"), "\"°C\"");
+    }
+    
+    #[test]
+    fn slugs_are_lowercase_ascii() {
+        assert_eq!(slug("Desk-PC.local"), "desk_pc_local");
+        assert_eq!(slug("top_gpu_temperature"), "top_gpu_temperature");
+        assert_eq!(slug("Büro"), "b_ro");
+    }
+    
+    #[test]
+    fn discovery_config_of_a_temperature() {
+        let config = client(None, None).discovery_config("cpu_temperature", "CPU temperature", Some("°C"));
+        let expected = concat!(
+            r#"{"name":"CPU temperature","unique_id":"risemode_desk_cpu_temperature","#,
+            r#""state_topic":"risemode/desk/cpu_temperature","availability_topic":"risemode/desk/availability","#,
+            r#""unit_of_measurement":"°C","state_class":"measurement","device_class":"temperature","#,
+            r#""device":{"identifiers":["risemode_desk"],"name":"Rise Mode display (desk)","#,
+            r#""manufacturer":"Rise Mode","model":"Aura Ice Black display","sw_version":""#,
+            env!("CARGO_PKG_VERSION"),
+            r#""}}"#
+        );
+        assert_eq!(config, expected);
+    }
+    
+    #[test]
+    fn discovery_config_of_the_status() {
+        let config = client(None, None).discovery_config("status", "Status", None);
+        assert!(config.contains(r#","entity_category":"diagnostic","device":"#), "{}", config);
+        assert!(!config.contains("unit_of_measurement"), "{}", config);
+    }
+    
+    #[test]
+    fn broker_ports_are_parsed() {
+        let parse = |broker| parse_broker(broker).unwrap();
+        assert_eq!(parse("broker.lan"), ("broker.lan".to_string(), DEFAULT_PORT));
+        assert_eq!(parse("broker.lan:8883"), ("broker.lan".to_string(), 8883));
+        assert_eq!(parse("192.168.1.2"), ("192.168.1.2".to_string(), DEFAULT_PORT));
+        assert_eq!(parse("192.168.1.2:1884"), ("192.168.1.2".to_string(), 1884));
+        assert_eq!(parse("fd00::2"), ("fd00::2".to_string(), DEFAULT_PORT));
+        assert_eq!(parse("[fd00::2]"), ("fd00::2".to_string(), DEFAULT_PORT));
+        assert_eq!(parse("[fd00::2]:1884"), ("fd00::2".to_string(), 1884));
+        
+        for broker in ["", ":1883", "broker.lan:port", "[fd00::2]:port", "[broker.lan]:1883"] {
+            assert!(parse_broker(broker).is_err(), "{:?}", broker);
+        }
+    }
+    
+    #[test]
+    fn failed_connects_wait_for_the_retry() {
+        // Nothing listens on a port that was just released
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut mqtt = Mqtt { port, ..client(None, None) };
+        
+        assert!(!mqtt.ensure_connected());
+        assert!(mqtt.connecting.is_some());
+        assert!(!settle(&mut mqtt));
+        assert!(mqtt.retry_at.is_some_and(|at| at > Instant::now()));
+        assert!(!mqtt.ensure_connected());
+        assert!(mqtt.connecting.is_none());
+    }
+    
+    #[test]
+    fn a_silent_broker_does_not_block_publishing() {
+        // Accepts the connection but never sends a CONNACK
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut mqtt = Mqtt { port, ..client(None, None) };
+        
+        let started = Instant::now();
+        mqtt.status("starting");
+        mqtt.reading("cpu_temperature", "CPU temperature", 45.3, "°C");
+        mqtt.keep_alive();
+        assert!(started.elapsed() < TIMEOUT / 2, "took {:?}", started.elapsed());
+        assert!(!settle(&mut mqtt));
+        drop(listener);
+    }
+    
+    #[test]
+    fn disconnecting_without_a_connection_does_nothing() {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut mqtt = Mqtt { port, ..client(None, None) };
+        
+        mqtt.disconnect();
+        assert!(mqtt.connecting.is_none());
+        assert!(mqtt.retry_at.is_none());
+    }
+    
+    #[test]
+    fn connects_and_announces_availability_and_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let broker = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut connect = vec![0u8; 2 + connect_prefix(0x26).len()];
+            stream.read_exact(&mut connect).unwrap();
+            stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+            
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).unwrap();
+            received
+        });
+        
+        let mut mqtt = Mqtt { port, ..client(None, None) };
+        // Sent once the connect started here has finished
+        mqtt.status("starting");
+        assert!(settle(&mut mqtt));
+        mqtt.disconnect();
+        drop(mqtt);
+        
+        let received = broker.join().unwrap();
+        let online = b"\x31\x22\x00\x1arisemode/desk/availabilityonline";
+        assert_eq!(received[..online.len()], online[..]);
+        let count = |needle: &[u8]| received.windows(needle.len()).filter(|window| *window == needle).count();
+        assert_eq!(count(b"homeassistant/sensor/risemode_desk/status/config"), 1);
+        assert_eq!(count(b"risemode/desk/statusstarting"), 1);
+        assert_eq!(count(b"risemode/desk/availabilityoffline"), 1);
+        assert!(received.ends_with(&[0xe0, 0x00]));
+    }
+}
@@ -0,0 +1,156 @@
+//! Names for displays that stick across reboots, kept next to the usage
+//! counters as `NAME = SELECTOR` lines.
+//!
+//! A name from `--device NAME=SELECTOR` takes precedence over a nickname.
+
+use anyhow::{Context, Result};
+use risemode_driver::DeviceSelector;
+use std::fs;
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::cli::DeviceSpec;
+use crate::usage;
+
+fn nicknames_path() -> PathBuf {
+    usage::state_dir().join("nicknames")
+}
+
+fn parse(text: &str) -> Vec<DeviceSpec> {
+    let mut nicknames = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        
+        let Some((name, selector)) = line.split_once('=').filter(|(name, _)| !name.trim().is_empty()) else {
+            warn!("Skipping line {} of the nicknames, expected NAME = SELECTOR: {:?}", number + 1, line);
+            continue;
+        };
+        match selector.trim().parse() {
+            Ok(selector) => nicknames.push(DeviceSpec { name: Some(name.trim().to_string()), selector }),
+            Err(e) => warn!("Skipping line {} of the nicknames: {}", number + 1, e),
+        }
+    }
+    nicknames
+}
+
+fn serialize(nicknames: &[DeviceSpec]) -> String {
+    nicknames
+        .iter()
+        .map(|spec| format!("{} = {}\n", spec.name.as_deref().unwrap_or_default(), spec.selector))
+        .collect()
+}
+
+/// Reads the saved nicknames; a missing file means there are none.
+pub fn load() -> Result<Vec<DeviceSpec>> {
+    let path = nicknames_path();
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(parse(&text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn save(nicknames: &[DeviceSpec]) -> Result<()> {
+    let dir = usage::state_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    
+    let path = nicknames_path();
+    fs::write(&path, serialize(nicknames)).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// `risemode-driver nickname`: lists the nicknames, or sets or removes one.
+pub fn run(name: Option<String>, selector: Option<DeviceSelector>, remove: bool) -> Result<()> {
+    let mut nicknames = load()?;
+    
+    let Some(name) = name else {
+        if nicknames.is_empty() {
+            println!("No nicknames yet, set one with: risemode-driver nickname NAME SELECTOR");
+        }
+        for spec in &nicknames {
+            println!("{} = {}", spec.name.as_deref().unwrap_or_default(), spec.selector);
+        }
+        return Ok(());
+    };
+    
+    edit(&mut nicknames, &name, selector, remove)?;
+    save(&nicknames)
+}
+
+/// Sets or, with `remove`, removes the nickname `name`.
+fn edit(nicknames: &mut Vec<DeviceSpec>, name: &str, selector: Option<DeviceSelector>, remove: bool) -> Result<()> {
+    let name = name.trim().to_string();
+    if name.is_empty() || name.contains('=') {
+        anyhow::bail!("a nickname can't be empty or contain '='");
+    }
+    
+    let before = nicknames.len();
+    nicknames.retain(|spec| spec.name.as_deref() != Some(name.as_str()));
+    
+    if remove {
+        if nicknames.len() == before {
+            anyhow::bail!("there is no nickname {:?}", name);
+        }
+        println!("Removed {}", name);
+    } else if let Some(selector) = selector {
+        println!("{} = {}", name, selector);
+        nicknames.push(DeviceSpec { name: Some(name), selector });
+    } else {
+        anyhow::bail!("give the display to name as serial:<SERIAL>, usb:<PORT> or a /dev/hidraw path");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn spec(name: &str, selector: &str) -> DeviceSpec {
+        DeviceSpec { name: Some(name.to_string()), selector: selector.parse().unwrap() }
+    }
+    
+    fn names(nicknames: &[DeviceSpec]) -> Vec<(String, DeviceSelector)> {
+        nicknames.iter().map(|spec| (spec.name.clone().unwrap(), spec.selector.clone())).collect()
+    }
+    
+    #[test]
+    fn nicknames_survive_a_round_trip() {
+        let nicknames = vec![
+            spec("top", "serial:A1B2"),
+            spec("bottom rad", "usb:1-4.2"),
+            spec("desk", "/dev/hidraw3"),
+        ];
+        
+        assert_eq!(names(&parse(&serialize(&nicknames))), names(&nicknames));
+    }
+    
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let text = "top = serial:A1B2\n\nno separator\n = usb:1-4\nbad = usb-1-4\ndesk=/dev/hidraw3\n";
+        
+        assert_eq!(names(&parse(text)), names(&[spec("top", "serial:A1B2"), spec("desk", "/dev/hidraw3")]));
+    }
+    
+    #[test]
+    fn names_must_be_set_and_free_of_equals_signs() {
+        let mut nicknames = Vec::new();
+        for name in ["", "  ", "a=b"] {
+            let selector = Some("serial:A1B2".parse().unwrap());
+            assert!(edit(&mut nicknames, name, selector, false).is_err(), "{:?}", name);
+        }
+        assert!(nicknames.is_empty());
+    }
+    
+    #[test]
+    fn nicknames_are_replaced_and_removed() {
+        let mut nicknames = vec![spec("top", "serial:A1B2"), spec("desk", "/dev/hidraw3")];
+        
+        edit(&mut nicknames, " top ", Some("usb:1-4".parse().unwrap()), false).unwrap();
+        assert_eq!(names(&nicknames), names(&[spec("desk", "/dev/hidraw3"), spec("top", "usb:1-4")]));
+        
+        edit(&mut nicknames, "desk", None, true).unwrap();
+        assert!(edit(&mut nicknames, "desk", None, true).is_err());
+        assert_eq!(names(&nicknames), names(&[spec("top", "usb:1-4")]));
+    }
+}
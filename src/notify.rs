@@ -0,0 +1,93 @@
+//! systemd readiness and watchdog notifications (`Type=notify`).
+//!
+//! Messages go to the datagram socket in `$NOTIFY_SOCKET`; without it (not
+//! started by systemd) every call does nothing.
+
+#[cfg(target_os = "linux")]
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+#[cfg(target_os = "linux")]
+pub struct Notifier {
+    socket: UnixDatagram,
+    address: SocketAddr,
+    watchdog: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl Notifier {
+    /// Connects to the socket systemd passed in the environment, if any.
+    pub fn from_env() -> Option<Self> {
+        use std::os::linux::net::SocketAddrExt;
+        
+        let path = std::env::var("NOTIFY_SOCKET").ok()?;
+        // A leading '@' names a socket in the abstract namespace
+        let address = match path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name),
+            None => SocketAddr::from_pathname(&path),
+        };
+        let address = match address {
+            Ok(address) => address,
+            Err(e) => {
+                tracing::warn!("Invalid NOTIFY_SOCKET {:?}: {}", path, e);
+                return None;
+            }
+        };
+        let socket = match UnixDatagram::unbound() {
+            Ok(socket) => socket,
+            Err(e) => {
+                tracing::warn!("Could not create the systemd notification socket: {}", e);
+                return None;
+            }
+        };
+        let watchdog = std::env::var("WATCHDOG_USEC").is_ok_and(|usec| usec.parse::<u64>().is_ok_and(|usec| usec > 0));
+        
+        Some(Self { socket, address, watchdog })
+    }
+    
+    fn send(&self, message: &str) {
+        // Notifications are best effort; systemd reports a missing READY itself
+        let _ = self.socket.send_to_addr(message.as_bytes(), &self.address);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub struct Notifier {
+    watchdog: bool,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Notifier {
+    /// There is no systemd outside Linux.
+    pub fn from_env() -> Option<Self> {
+        None
+    }
+    
+    fn send(&self, _message: &str) {}
+}
+
+impl Notifier {
+    /// The displays are open and the send loop is starting.
+    pub fn ready(&self) {
+        self.send("READY=1");
+    }
+    
+    /// Shown by `systemctl status`.
+    pub fn status(&self, status: &str) {
+        self.send(&format!("STATUS={}", status));
+    }
+    
+    /// Tells systemd the loop is alive, when `WatchdogSec=` is set.
+    pub fn watchdog(&self) {
+        if self.watchdog {
+            self.send("WATCHDOG=1");
+        }
+    }
+    
+    pub fn stopping(&self) {
+        self.send("STOPPING=1");
+    }
+    
+    pub fn reloading(&self) {
+        self.send("RELOADING=1");
+    }
+}
@@ -0,0 +1,152 @@
+//! Encoding of the frames understood by the display.
+
+use std::fmt;
+
+/// Size of every report sent to the display.
+pub const FRAME_LEN: usize = 24;
+
+/// Highest temperature a frame can hold: byte 0 is the integer part.
+pub const MAX_TEMPERATURE: f32 = 255.9;
+
+/// Returned when a value doesn't fit in its frame field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameError {
+    TemperatureOutOfRange(f32),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TemperatureOutOfRange(temp) => {
+                write!(f, "temperature must be between 0 and {}, got {}", MAX_TEMPERATURE, temp)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// One report for the display, built field by field.
+///
+/// Layout as far as it is known: byte 0 = integer part of the temperature,
+/// byte 1 = tenths (0-9), the rest zero. A new frame is blank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Frame {
+    bytes: [u8; FRAME_LEN],
+}
+
+impl Frame {
+    /// A blank frame, which leaves the display without a reading.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// A frame showing `temp`, see [`Frame::set_temperature`].
+    pub fn temperature(temp: f32) -> Result<Self, FrameError> {
+        let mut frame = Self::new();
+        frame.set_temperature(temp)?;
+        Ok(frame)
+    }
+    
+    /// Shows `temp`, rounded to a tenth. Fails for values outside 0 to
+    /// [`MAX_TEMPERATURE`] (and NaN) instead of sending something else.
+    pub fn set_temperature(&mut self, temp: f32) -> Result<&mut Self, FrameError> {
+        if !(0.0..=MAX_TEMPERATURE).contains(&temp) {
+            return Err(FrameError::TemperatureOutOfRange(temp));
+        }
+        
+        // Work in whole tenths: 45.3 is 45.2999... as an f32 and would otherwise
+        // truncate to 45.2
+        let tenths = (temp * 10.0).round() as u16;
+        self.bytes[0] = (tenths / 10) as u8;
+        self.bytes[1] = (tenths % 10) as u8;
+        Ok(self)
+    }
+    
+    pub fn to_bytes(&self) -> [u8; FRAME_LEN] {
+        self.bytes
+    }
+}
+
+/// The bytes in hex, e.g. `2d 03 00 ...`.
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.bytes)
+    }
+}
+
+/// A report the device sent to the host.
+///
+/// Its layout is undocumented: some firmware is said to report the coolant
+/// temperature and pump speed, but no field has been identified yet, so it is
+/// only kept and shown as raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputReport {
+    bytes: Vec<u8>,
+}
+
+impl InputReport {
+    pub fn new(bytes: &[u8]) -> Self {
+        Self { bytes: bytes.to_vec() }
+    }
+    
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl fmt::Display for InputReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.bytes)
+    }
+}
+
+/// Writes bytes as space-separated hex, `2d 03 00 ...`.
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            f.write_str(" ")?;
+        }
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn digits(temp: f32) -> (u8, u8) {
+        let bytes = Frame::temperature(temp).unwrap().to_bytes();
+        (bytes[0], bytes[1])
+    }
+    
+    #[test]
+    fn values_the_frame_cannot_hold_are_rejected() {
+        for temp in [f32::NAN, -0.1, -40.0, MAX_TEMPERATURE + 0.1, 1000.0, f32::INFINITY] {
+            let error = Frame::temperature(temp).unwrap_err();
+            assert!(matches!(error, FrameError::TemperatureOutOfRange(_)), "{}", temp);
+        }
+    }
+    
+    #[test]
+    fn limits_are_accepted() {
+        assert_eq!(digits(0.0), (0, 0));
+        assert_eq!(digits(255.9), (255, 9));
+    }
+    
+    #[test]
+    fn tenths_are_rounded_not_truncated() {
+        assert_eq!(digits(45.3), (45, 3));
+        assert_eq!(digits(42.94), (42, 9));
+        assert_eq!(digits(42.95), (43, 0));
+        assert_eq!(digits(99.99), (100, 0));
+    }
+    
+    #[test]
+    fn only_the_first_two_bytes_are_set() {
+        let bytes = Frame::temperature(42.5).unwrap().to_bytes();
+        assert!(bytes[2..].iter().all(|&byte| byte == 0));
+        assert_eq!(Frame::new().to_bytes(), [0; FRAME_LEN]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Escalating recovery when the display keeps failing.
+//!
+//! Each failed connection attempt moves one step up the ladder: reopen the
+//! handle, re-enumerate HID devices, reset the display over USB, and finally
+//! keep re-enumerating with an increasing delay. A successful write round
+//! starts over from the bottom. Waits are cut short when the display is
+//! plugged in.
+
+use hidapi::HidApi;
+use risemode_driver::{device, usb, HotplugEvent, HotplugMonitor};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::cli::Options;
+use crate::usage::Usage;
+
+const USB_DIAGNOSTICS_AFTER_FAILURES: u32 = 3; // Consecutive write failures before inspecting USB power
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Time for udev to set up a newly plugged display before it is opened.
+pub const HOTPLUG_SETTLE: Duration = Duration::from_secs(1);
+
+/// Failure counts kept across reconnect attempts.
+#[derive(Debug, Default)]
+pub struct Recovery {
+    write_failures: u32,
+    failed_attempts: u32,
+}
+
+impl Recovery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Records a failed write and prints USB power diagnostics once the
+    /// failures look persistent.
+    pub fn write_failed(&mut self, path: &str) {
+        self.write_failures += 1;
+        if self.write_failures == USB_DIAGNOSTICS_AFTER_FAILURES {
+            usb::print_diagnostics(path);
+        }
+    }
+    
+    /// Called after a frame reached every device.
+    pub fn frames_written(&mut self) {
+        self.write_failures = 0;
+        self.failed_attempts = 0;
+    }
+    
+    /// Takes the next recovery step after a failed attempt, before the caller
+    /// tries to connect again.
+    pub fn escalate(
+        &mut self,
+        api: &mut HidApi,
+        options: &Options,
+        usage: &mut Usage,
+        hotplug: Option<&HotplugMonitor>,
+    ) {
+        self.failed_attempts += 1;
+        
+        match self.failed_attempts {
+            1 => {
+                info!("Recovery step 1: reopening the device");
+                usage.counters.reopens += 1;
+                if pause(hotplug, Duration::from_secs(1)) {
+                    refresh(api);
+                }
+            }
+            2 => {
+                info!("Recovery step 2: re-enumerating HID devices");
+                usage.counters.reenumerations += 1;
+                refresh(api);
+            }
+            3 => {
+                let usb_dirs = usb_devices(api, options);
+                if usb_dirs.is_empty() {
+                    info!("Recovery step 3: display not enumerated, skipping USB reset");
+                } else {
+                    info!("Recovery step 3: resetting the display over USB");
+                    usage.counters.usb_resets += 1;
+                    for usb_dir in &usb_dirs {
+                        if let Err(e) = usb::power_cycle(usb_dir) {
+                            warn!("USB reset of {} failed: {:#}", usb_dir.display(), e);
+                        }
+                    }
+                    
+                    // Give the kernel time to enumerate the display again
+                    thread::sleep(Duration::from_secs(2));
+                }
+                refresh(api);
+            }
+            attempt => {
+                let delay = Duration::from_secs(1 << (attempt - 3).min(5)).min(MAX_BACKOFF);
+                info!("Recovery step 4: waiting {} s before re-enumerating (attempt {})", delay.as_secs(), attempt);
+                usage.counters.reenumerations += 1;
+                pause(hotplug, delay);
+                refresh(api);
+            }
+        }
+    }
+}
+
+/// Sleeps for `delay`, or until the display is plugged in. Returns whether
+/// it was.
+fn pause(hotplug: Option<&HotplugMonitor>, delay: Duration) -> bool {
+    let Some(hotplug) = hotplug else {
+        thread::sleep(delay);
+        return false;
+    };
+    
+    match hotplug.wait(delay) {
+        Ok(Some(HotplugEvent::Added)) => {
+            info!("Display plugged in");
+            // Let udev create the hidraw node and apply permissions
+            thread::sleep(HOTPLUG_SETTLE);
+            true
+        }
+        Ok(_) => false,
+        Err(e) => {
+            warn!("Waiting for hotplug events failed: {}", e);
+            thread::sleep(delay);
+            false
+        }
+    }
+}
+
+pub fn refresh(api: &mut HidApi) {
+    if let Err(e) = api.refresh_devices() {
+        warn!("Failed to refresh the HID device list: {}", e);
+    }
+}
+
+/// USB devices behind the matching HID interfaces, one per physical display.
+/// Displays left out by `--device` are left alone.
+pub fn usb_devices(api: &HidApi, options: &Options) -> Vec<PathBuf> {
+    let mut usb_dirs = Vec::new();
+    
+    for info in device::find_devices(api, options.vendor_id(), options.product_id()) {
+        if crate::selected(options, info).is_some()
+            && let Some(usb_dir) = usb::sysfs_device(&info.path().to_string_lossy())
+            && !usb_dirs.contains(&usb_dir)
+        {
+            usb_dirs.push(usb_dir);
+        }
+    }
+    
+    usb_dirs
+}
@@ -0,0 +1,168 @@
+//! Optional Landlock self-sandboxing for the long-running daemon.
+//!
+//! Once applied, the process can only touch the paths the driver needs:
+//! hidraw nodes, sysfs, procfs, the udev database and its state directory.
+//! Landlock can't be lifted again, so this runs after startup.
+
+use anyhow::Result;
+use std::path::Path;
+
+#[cfg(target_os = "linux")]
+mod landlock {
+    use anyhow::{Context, Result};
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    
+    // Filesystem access rights of Landlock ABI v1
+    pub const WRITE_FILE: u64 = 1 << 1;
+    pub const READ_FILE: u64 = 1 << 2;
+    pub const READ_DIR: u64 = 1 << 3;
+    pub const REMOVE_FILE: u64 = 1 << 5;
+    pub const MAKE_REG: u64 = 1 << 8;
+    const HANDLED_V1: u64 = (1 << 13) - 1;
+    
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+    
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+    
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+    
+    /// A ruleset being built; dropped (closed) once applied.
+    pub struct Ruleset {
+        fd: libc::c_int,
+    }
+    
+    impl Ruleset {
+        pub fn new() -> io::Result<Self> {
+            let attr = RulesetAttr { handled_access_fs: HANDLED_V1 };
+            // SAFETY: attr is valid for the call and its size is passed along
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_create_ruleset,
+                    &attr as *const RulesetAttr,
+                    std::mem::size_of::<RulesetAttr>(),
+                    0u32,
+                )
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            
+            Ok(Self { fd: fd as libc::c_int })
+        }
+        
+        /// Allows `access` beneath `path`. Paths that don't exist are skipped.
+        pub fn allow(&mut self, path: &Path, access: u64) -> Result<()> {
+            let c_path = CString::new(path.as_os_str().as_bytes())?;
+            // SAFETY: c_path is a valid NUL-terminated string
+            let parent_fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+            if parent_fd < 0 {
+                return Ok(());
+            }
+            
+            let attr = PathBeneathAttr { allowed_access: access, parent_fd };
+            // SAFETY: attr outlives the call, and parent_fd is closed right after
+            let result = unsafe {
+                let result = libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    self.fd,
+                    RULE_PATH_BENEATH,
+                    &attr as *const PathBeneathAttr,
+                    0u32,
+                );
+                libc::close(parent_fd);
+                result
+            };
+            if result < 0 {
+                return Err(io::Error::last_os_error())
+                    .with_context(|| format!("Failed to add a Landlock rule for {}", path.display()));
+            }
+            
+            Ok(())
+        }
+        
+        /// Restricts this process (and all its threads created later) to the rules.
+        pub fn restrict_self(self) -> Result<()> {
+            // SAFETY: plain prctl/syscall calls without pointers
+            unsafe {
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 {
+                    return Err(io::Error::last_os_error()).context("Failed to set no_new_privs");
+                }
+                if libc::syscall(libc::SYS_landlock_restrict_self, self.fd, 0u32) < 0 {
+                    return Err(io::Error::last_os_error()).context("Failed to enforce the Landlock ruleset");
+                }
+            }
+            
+            Ok(())
+        }
+    }
+    
+    impl Drop for Ruleset {
+        fn drop(&mut self) {
+            // SAFETY: the descriptor is owned by the ruleset
+            unsafe {
+                libc::close(self.fd);
+            }
+        }
+    }
+}
+
+/// Restricts filesystem access to what the daemon needs. `state_dir` must
+/// already exist, as must `textfile_dir` (for `--textfile`) when given.
+/// `resolve_names` keeps the resolver configuration readable, for `--mqtt`.
+/// Returns `Ok(false)` if the kernel doesn't support Landlock.
+#[cfg(target_os = "linux")]
+pub fn apply(state_dir: &Path, textfile_dir: Option<&Path>, resolve_names: bool) -> Result<bool> {
+    use landlock::*;
+    
+    let mut ruleset = match Ruleset::new() {
+        Ok(ruleset) => ruleset,
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENOSYS) | Some(libc::EOPNOTSUPP)) => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    
+    // hidraw nodes are opened read-write, also after hotplug
+    ruleset.allow(Path::new("/dev"), READ_FILE | WRITE_FILE | READ_DIR)?;
+    // Sensors, USB power management and power-cycling through authorized
+    ruleset.allow(Path::new("/sys"), READ_FILE | WRITE_FILE | READ_DIR)?;
+    ruleset.allow(Path::new("/proc"), READ_FILE | READ_DIR)?;
+    // hidapi enumerates devices through libudev
+    ruleset.allow(Path::new("/run/udev"), READ_FILE | READ_DIR)?;
+    ruleset.allow(Path::new("/etc/udev"), READ_FILE | READ_DIR)?;
+    // lm-sensors labels for --hwmon-label, where installed
+    if Path::new("/etc/sensors3.conf").exists() {
+        ruleset.allow(Path::new("/etc/sensors3.conf"), READ_FILE)?;
+    }
+    if Path::new("/etc/sensors.d").exists() {
+        ruleset.allow(Path::new("/etc/sensors.d"), READ_FILE | READ_DIR)?;
+    }
+    // The MQTT broker is looked up again on every reconnect
+    if resolve_names {
+        for file in ["/etc/hosts", "/etc/resolv.conf", "/etc/nsswitch.conf", "/etc/gai.conf"] {
+            ruleset.allow(Path::new(file), READ_FILE)?;
+        }
+    }
+    // Usage counters are replaced atomically with a rename
+    ruleset.allow(state_dir, READ_FILE | WRITE_FILE | READ_DIR | MAKE_REG | REMOVE_FILE)?;
+    // So is the metrics file
+    if let Some(dir) = textfile_dir {
+        ruleset.allow(dir, READ_FILE | WRITE_FILE | READ_DIR | MAKE_REG | REMOVE_FILE)?;
+    }
+    
+    ruleset.restrict_self()?;
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(_state_dir: &Path, _textfile_dir: Option<&Path>, _resolve_names: bool) -> Result<bool> {
+    Ok(false)
+}
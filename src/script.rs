@@ -0,0 +1,185 @@
+//! `risemode-driver script`: plays a file of timed display commands, for
+//! demos, testing and product photos.
+//!
+//! One command per line, `#` starts a comment:
+//!
+//! ```text
+//! show 42.5      # show a value (0 to 255.9)
+//! wait 2s        # pause; ms, s or m, plain numbers are milliseconds
+//! blank          # clear the display
+//! ```
+
+use anyhow::{Context, Result};
+use hidapi::HidApi;
+use risemode_driver::protocol::Frame;
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::cli::Options;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Show(f32),
+    Blank,
+    Wait(Duration),
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 1.0)
+    } else if let Some(s) = value.strip_suffix('s') {
+        (s, 1000.0)
+    } else if let Some(m) = value.strip_suffix('m') {
+        (m, 60_000.0)
+    } else {
+        (value, 1.0)
+    };
+    
+    // Negative, NaN and absurdly long durations (e.g. `inf`) all fail here
+    let millis: f64 = number.parse().ok()?;
+    Duration::try_from_secs_f64(millis * scale / 1000.0).ok()
+}
+
+fn parse_line(line: &str) -> Result<Option<Step>> {
+    let line = line.split('#').next().unwrap_or("").trim();
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return Ok(None);
+    };
+    let argument = words.next();
+    if words.next().is_some() {
+        anyhow::bail!("too many arguments");
+    }
+    
+    let step = match (command, argument) {
+        ("show", Some(value)) => {
+            let temp: f32 = value.replacen(',', ".", 1).parse().with_context(|| format!("invalid value {:?}", value))?;
+            Frame::temperature(temp)?;
+            Step::Show(temp)
+        }
+        ("blank", None) => Step::Blank,
+        ("wait", Some(value)) => Step::Wait(parse_duration(value).with_context(|| format!("invalid duration {:?}", value))?),
+        ("rgb" | "duty", _) => anyhow::bail!("{} is not supported: the display only shows a number", command),
+        ("show" | "wait", None) => anyhow::bail!("{} needs an argument", command),
+        ("blank", Some(_)) => anyhow::bail!("blank takes no argument"),
+        _ => anyhow::bail!("unknown command {:?}", command),
+    };
+    
+    Ok(Some(step))
+}
+
+/// Parses the whole file up front so a typo near the end doesn't stop a
+/// demo halfway.
+fn parse(text: &str) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    
+    for (number, line) in text.lines().enumerate() {
+        if let Some(step) = parse_line(line).with_context(|| format!("line {}", number + 1))? {
+            steps.push(step);
+        }
+    }
+    
+    Ok(steps)
+}
+
+pub fn run(api: &HidApi, options: &Options, path: &Path) -> Result<()> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let steps = parse(&text).with_context(|| format!("Invalid script {}", path.display()))?;
+    let devices = crate::open_devices(api, options)?;
+    
+    for step in &steps {
+        let frame = match *step {
+            Step::Show(temp) => {
+                println!("show {:.1}", temp);
+                Frame::temperature(temp)?
+            }
+            Step::Blank => {
+                println!("blank");
+                Frame::new()
+            }
+            Step::Wait(duration) => {
+                thread::sleep(duration);
+                continue;
+            }
+        };
+        
+        for device in &devices {
+            device.send(&frame)?;
+        }
+    }
+    
+    println!("Script finished ({} steps)", steps.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn step(line: &str) -> Option<Step> {
+        parse_line(line).unwrap()
+    }
+    
+    fn error(line: &str) -> String {
+        format!("{:#}", parse_line(line).unwrap_err())
+    }
+    
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(step("show 42.5"), Some(Step::Show(42.5)));
+        assert_eq!(step("show 42,5"), Some(Step::Show(42.5)));
+        assert_eq!(step("  blank  "), Some(Step::Blank));
+        assert_eq!(step("wait 250"), Some(Step::Wait(Duration::from_millis(250))));
+    }
+    
+    #[test]
+    fn durations_take_units() {
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+    }
+    
+    #[test]
+    fn impossible_durations_are_rejected() {
+        for value in ["-1s", "inf", "infs", "1e300s", "NaN", "soon", "2h", ""] {
+            assert_eq!(parse_duration(value), None, "{:?}", value);
+        }
+    }
+    
+    #[test]
+    fn comments_and_empty_lines_are_skipped() {
+        assert_eq!(step(""), None);
+        assert_eq!(step("   # only a comment"), None);
+        assert_eq!(step("show 30 # warm"), Some(Step::Show(30.0)));
+    }
+    
+    #[test]
+    fn unsupported_commands_say_why() {
+        assert!(error("rgb ff0000").contains("rgb is not supported"));
+        assert!(error("duty 50").contains("duty is not supported"));
+        assert!(error("flash").contains("unknown command"));
+    }
+    
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert!(error("show").contains("show needs an argument"));
+        assert!(error("wait").contains("wait needs an argument"));
+        assert!(error("blank 1").contains("blank takes no argument"));
+        assert!(error("show 1 2").contains("too many arguments"));
+        assert!(error("show hot").contains("invalid value"));
+        assert!(error("show 256").contains("temperature must be between"));
+        assert!(error("wait inf").contains("invalid duration"));
+    }
+    
+    #[test]
+    fn errors_name_the_line() {
+        let steps = parse("show 40\nwait 1s\n\nblank\n").unwrap();
+        assert_eq!(steps, [Step::Show(40.0), Step::Wait(Duration::from_secs(1)), Step::Blank]);
+        
+        let error = parse("show 40\nwait 1e300s\n").unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 2: invalid duration \"1e300s\"");
+    }
+}
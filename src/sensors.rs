@@ -0,0 +1,703 @@
+//! Temperature sources read from the host.
+
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+mod lmsensors;
+
+pub use lmsensors::{chip_name, SensorsConfig};
+
+#[cfg(windows)]
+mod windows;
+
+#[cfg(windows)]
+pub use windows::{AcpiThermalSource, LibreHardwareMonitorSource};
+
+#[cfg(target_os = "macos")]
+mod macos;
+
+#[cfg(target_os = "macos")]
+pub use macos::SmcSource;
+
+/// Anything that can produce a temperature in °C for the display.
+///
+/// Implement this to feed the display from your own sensor.
+pub trait TemperatureSource {
+    /// Short name used in log messages.
+    fn name(&self) -> &str;
+    
+    /// Takes a fresh reading, in °C.
+    fn read(&mut self) -> Result<f32>;
+    
+    /// Name and health of each source this one falls back between, for
+    /// status output. Empty for sources that don't keep track.
+    fn health(&self) -> Vec<(&str, &SourceHealth)> {
+        Vec::new()
+    }
+}
+
+/// CPU package temperature from `/sys/class/hwmon/` (coretemp, k10temp, zenpower).
+///
+/// Dual-socket systems and boards with two identical chips expose the same
+/// name more than once, so every match is read and the hottest is reported,
+/// unless the source is pinned to one chip with [`HwmonSource::with_device`].
+///
+/// By default any chip whose name looks like a CPU sensor is used, and its
+/// `temp1_input` is read. [`HwmonSource::chip`], [`HwmonSource::input`] and
+/// [`HwmonSource::label`] pin the chip and the input explicitly.
+#[derive(Debug, Default)]
+pub struct HwmonSource {
+    device: Option<String>,
+    chip: Option<String>,
+    input: Option<String>,
+    label: Option<String>,
+    aggregate: HwmonAggregate,
+    sensors_config: Option<SensorsConfig>,
+}
+
+/// How the inputs of one hwmon chip are combined into a reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HwmonAggregate {
+    /// One input: `temp1_input`, or the one chosen with [`HwmonSource::input`]
+    /// or [`HwmonSource::label`]
+    #[default]
+    Single,
+    /// Hottest of all `temp*_input` files, e.g. per-CCD readings that run
+    /// hotter than the package value
+    Max,
+    /// Average of all `temp*_input` files
+    Average,
+}
+
+impl HwmonSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Only reads the chip belonging to `device`, as returned by [`hwmon_device_id`]
+    /// (e.g. `0000:00:18.3` or `coretemp.1`).
+    pub fn with_device(device: impl Into<String>) -> Self {
+        Self { device: Some(device.into()), ..Self::default() }
+    }
+    
+    /// Only reads chips whose `name` is exactly `chip` (e.g. `k10temp`),
+    /// instead of guessing from the name.
+    pub fn chip(mut self, chip: impl Into<String>) -> Self {
+        self.chip = Some(chip.into());
+        self
+    }
+    
+    /// Reads this file of the chip (e.g. `temp3_input`) instead of `temp1_input`.
+    pub fn input(mut self, input: impl Into<String>) -> Self {
+        self.input = Some(input.into());
+        self
+    }
+    
+    /// Reads the input whose `temp*_label` is `label` (e.g. `Tdie`) instead of
+    /// `temp1_input`.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+    
+    /// Also matches [`HwmonSource::label`] against the labels set in the
+    /// lm-sensors configuration, so the names `sensors` shows work too.
+    pub fn sensors_config(mut self, config: SensorsConfig) -> Self {
+        self.sensors_config = Some(config);
+        self
+    }
+    
+    /// Combines all inputs of the chip instead of reading a single one.
+    pub fn aggregate(mut self, aggregate: HwmonAggregate) -> Self {
+        self.aggregate = aggregate;
+        self
+    }
+    
+    fn matches(&self, hwmon: &Path, name: &str) -> bool {
+        let name_matches = match &self.chip {
+            Some(chip) => name == chip,
+            // Look for common CPU temperature sensor names
+            None => {
+                name.contains("coretemp") || name.contains("k10temp") || name.contains("zenpower") || name.contains("cpu")
+            }
+        };
+        
+        name_matches && self.device.as_deref().is_none_or(|device| hwmon_device_id(hwmon).as_deref() == Some(device))
+    }
+    
+    /// The input file to read on a matching chip.
+    fn input_path(&self, hwmon: &Path) -> Option<PathBuf> {
+        match (&self.label, &self.input) {
+            (Some(label), _) => self.configured_input(hwmon, label).or_else(|| find_labeled_input(hwmon, label)),
+            (None, Some(input)) => Some(hwmon.join(input)),
+            // temp1_input is the package temperature on the common CPU drivers
+            (None, None) => Some(hwmon.join("temp1_input")),
+        }
+    }
+    
+    /// The input the lm-sensors configuration labels `label` on this chip.
+    fn configured_input(&self, hwmon: &Path, label: &str) -> Option<PathBuf> {
+        let config = self.sensors_config.as_ref()?;
+        let chip = chip_name(hwmon)?;
+        
+        temp_features(hwmon)
+            .into_iter()
+            .find(|feature| config.label(&chip, feature) == Some(label) && !config.is_ignored(&chip, feature))
+            .map(|feature| hwmon.join(format!("{}_input", feature)))
+    }
+    
+    /// Reads a matching chip according to the aggregation mode.
+    fn read_chip(&self, hwmon: &Path) -> Option<f32> {
+        match self.aggregate {
+            HwmonAggregate::Single => read_millidegrees(&self.input_path(hwmon)?),
+            HwmonAggregate::Max => all_inputs(hwmon).into_iter().reduce(f32::max),
+            HwmonAggregate::Average => {
+                let temps = all_inputs(hwmon);
+                (!temps.is_empty()).then(|| temps.iter().sum::<f32>() / temps.len() as f32)
+            }
+        }
+    }
+}
+
+/// The temperature features of a hwmon chip (`temp1`, `temp2`...), in order.
+pub fn temp_features(hwmon: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(hwmon) else {
+        return Vec::new();
+    };
+    
+    let mut features: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let feature = file_name.strip_suffix("_input")?;
+            feature.strip_prefix("temp")?.parse::<u32>().ok()?;
+            Some(feature.to_string())
+        })
+        .collect();
+    features.sort_by_key(|feature| feature[4..].parse::<u32>().unwrap_or(0));
+    features
+}
+
+/// Readings of every `temp*_input` file of a hwmon chip.
+fn all_inputs(hwmon: &Path) -> Vec<f32> {
+    let Ok(entries) = fs::read_dir(hwmon) else {
+        return Vec::new();
+    };
+    
+    entries
+        .flatten()
+        .filter(|entry| {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            file_name.starts_with("temp") && file_name.ends_with("_input")
+        })
+        .filter_map(|entry| read_millidegrees(&entry.path()))
+        .collect()
+}
+
+impl TemperatureSource for HwmonSource {
+    fn name(&self) -> &str {
+        "hwmon"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        // Search for CPU temperature in /sys/class/hwmon/
+        let hwmon_path = "/sys/class/hwmon";
+        let mut hottest: Option<f32> = None;
+        
+        if let Ok(entries) = fs::read_dir(hwmon_path) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                
+                if let Ok(name) = fs::read_to_string(path.join("name"))
+                    && self.matches(&path, name.trim())
+                    && let Some(temp) = self.read_chip(&path)
+                {
+                    hottest = Some(hottest.map_or(temp, |hottest| hottest.max(temp)));
+                }
+            }
+        }
+        
+        if let Some(temp) = hottest {
+            return Ok(temp);
+        }
+        
+        anyhow::bail!("No hwmon temperature sensors found")
+    }
+}
+
+/// Identifies the device behind a hwmon entry: a PCI address such as
+/// `0000:00:18.3` for k10temp, or a platform name such as `coretemp.0`.
+/// Unlike `hwmonN`, this stays the same across reboots.
+pub fn hwmon_device_id(hwmon: &Path) -> Option<String> {
+    let target = fs::read_link(hwmon.join("device")).ok()?;
+    Some(target.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Which of the temperatures reported by an amdgpu chip to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GpuTemperature {
+    /// Edge of the die; reported by every amdgpu card
+    #[default]
+    Edge,
+    /// Hotspot of the die (Vega and newer), usually the hottest value
+    Junction,
+    /// HBM/VRAM, on cards that expose it
+    Memory,
+}
+
+impl GpuTemperature {
+    /// Value of the matching `temp*_label` file in hwmon.
+    fn label(self) -> &'static str {
+        match self {
+            GpuTemperature::Edge => "edge",
+            GpuTemperature::Junction => "junction",
+            GpuTemperature::Memory => "mem",
+        }
+    }
+}
+
+/// AMD GPU temperature from the `amdgpu` hwmon driver. With several cards,
+/// the hottest is reported.
+#[derive(Debug, Default)]
+pub struct AmdGpuSource {
+    sensor: GpuTemperature,
+}
+
+impl AmdGpuSource {
+    pub fn new(sensor: GpuTemperature) -> Self {
+        Self { sensor }
+    }
+}
+
+impl TemperatureSource for AmdGpuSource {
+    fn name(&self) -> &str {
+        "amdgpu"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        let mut hottest: Option<f32> = None;
+        
+        if let Ok(entries) = fs::read_dir("/sys/class/hwmon") {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                
+                if fs::read_to_string(path.join("name")).is_ok_and(|name| name.trim() == "amdgpu")
+                    && let Some(input) = find_labeled_input(&path, self.sensor.label())
+                    && let Some(temp) = read_millidegrees(&input)
+                {
+                    hottest = Some(hottest.map_or(temp, |hottest| hottest.max(temp)));
+                }
+            }
+        }
+        
+        if let Some(temp) = hottest {
+            return Ok(temp);
+        }
+        
+        anyhow::bail!("No amdgpu {} temperature found", self.sensor.label())
+    }
+}
+
+/// Finds the `temp*_input` file whose `temp*_label` reads `label`.
+fn find_labeled_input(hwmon: &Path, label: &str) -> Option<PathBuf> {
+    for entry in fs::read_dir(hwmon).ok()?.flatten() {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        
+        if let Some(prefix) = file_name.strip_suffix("_label")
+            && prefix.starts_with("temp")
+            && fs::read_to_string(entry.path()).is_ok_and(|value| value.trim() == label)
+        {
+            return Some(hwmon.join(format!("{}_input", prefix)));
+        }
+    }
+    
+    None
+}
+
+/// Reads a sysfs temperature file, which holds millidegrees Celsius.
+fn read_millidegrees(path: &Path) -> Option<f32> {
+    let temp_millidegrees = fs::read_to_string(path).ok()?.trim().parse::<i32>().ok()?;
+    Some(temp_millidegrees as f32 / 1000.0)
+}
+
+/// CPU temperature from `/sys/class/thermal/` zones, for systems without a
+/// hwmon driver for the CPU.
+#[derive(Debug, Default)]
+pub struct ThermalZoneSource;
+
+impl TemperatureSource for ThermalZoneSource {
+    fn name(&self) -> &str {
+        "thermal"
+    }
+    
+    #[allow(clippy::collapsible_if)]
+    fn read(&mut self) -> Result<f32> {
+        // Try reading from thermal zones
+        let thermal_path = "/sys/class/thermal";
+        
+        if let Ok(entries) = fs::read_dir(thermal_path) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let name = entry.file_name();
+                
+                if name.to_string_lossy().starts_with("thermal_zone") {
+                    // Check if this is a CPU thermal zone
+                    if let Ok(zone_type) = fs::read_to_string(path.join("type")) {
+                        let zone_type = zone_type.trim();
+                        
+                        if zone_type.contains("cpu") || zone_type.contains("x86_pkg_temp") {
+                            if let Ok(temp_str) = fs::read_to_string(path.join("temp")) {
+                                if let Ok(temp_millidegrees) = temp_str.trim().parse::<i32>() {
+                                    return Ok(temp_millidegrees as f32 / 1000.0);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        
+        anyhow::bail!("No thermal zone temperature found")
+    }
+}
+
+/// Number of recent reads a source's health is judged on.
+const HEALTH_WINDOW: usize = 20;
+/// Bad reads within the window that put a source in quarantine.
+const QUARANTINE_FAILURES: usize = 5;
+const QUARANTINE_TIME: Duration = Duration::from_secs(60);
+const MAX_QUARANTINE: Duration = Duration::from_secs(15 * 60);
+/// Reads slower than this count against a source even when they succeed.
+const SLOW_READ: Duration = Duration::from_millis(500);
+
+/// Track record of one source in a [`FallbackSource`].
+#[derive(Debug, Default)]
+pub struct SourceHealth {
+    /// Whether each recent read was good, and how long it took
+    recent: VecDeque<(bool, Duration)>,
+    quarantined_until: Option<Instant>,
+    /// Quarantines in a row without a clean window in between
+    quarantines: u32,
+}
+
+impl SourceHealth {
+    /// Share of the recent reads that failed or were slow, from 0 to 1.
+    pub fn failure_rate(&self) -> f32 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        self.recent.iter().filter(|(good, _)| !good).count() as f32 / self.recent.len() as f32
+    }
+    
+    pub fn average_latency(&self) -> Duration {
+        let total: Duration = self.recent.iter().map(|(_, latency)| *latency).sum();
+        total / self.recent.len().max(1) as u32
+    }
+    
+    pub fn is_quarantined(&self) -> bool {
+        self.quarantined_until.is_some_and(|until| Instant::now() < until)
+    }
+    
+    /// Whether any recent read failed or was slow, or the source is quarantined.
+    pub fn is_degraded(&self) -> bool {
+        self.is_quarantined() || self.recent.iter().any(|(good, _)| !good)
+    }
+    
+    /// Records a read and returns how long the source is quarantined for if
+    /// this read tipped it over. Slow reads count as bad even if they worked.
+    fn record(&mut self, ok: bool, latency: Duration) -> Option<Duration> {
+        self.recent.push_back((ok && latency < SLOW_READ, latency));
+        if self.recent.len() > HEALTH_WINDOW {
+            self.recent.pop_front();
+        }
+        
+        let failures = self.recent.iter().filter(|(good, _)| !good).count();
+        if failures >= QUARANTINE_FAILURES {
+            // A source that keeps coming back broken stays out longer each time
+            let time = (QUARANTINE_TIME * 2u32.pow(self.quarantines.min(4))).min(MAX_QUARANTINE);
+            self.quarantines += 1;
+            self.quarantined_until = Some(Instant::now() + time);
+            self.recent.clear();
+            return Some(time);
+        }
+        if failures == 0 && self.recent.len() == HEALTH_WINDOW {
+            self.quarantines = 0;
+        }
+        
+        None
+    }
+}
+
+impl fmt::Display for SourceHealth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(until) = self.quarantined_until.filter(|_| self.is_quarantined()) {
+            return write!(f, "quarantined for {} s", until.saturating_duration_since(Instant::now()).as_secs());
+        }
+        
+        let bad = self.recent.iter().filter(|(good, _)| !good).count();
+        if bad > 0 {
+            write!(f, "{} of {} recent reads failed or were slow, ", bad, self.recent.len())?;
+        } else {
+            write!(f, "ok, ")?;
+        }
+        write!(f, "{} ms on average", self.average_latency().as_millis())
+    }
+}
+
+/// Tries each source in order and returns the first successful reading.
+///
+/// Sources that keep failing or answering slowly (5 of their last 20 reads)
+/// are quarantined for a minute, doubling up to 15 minutes each time they
+/// relapse, so a dying sensor doesn't slow down every read. Quarantined
+/// sources are still tried as a last resort when every other source fails.
+pub struct FallbackSource {
+    sources: Vec<Box<dyn TemperatureSource>>,
+    health: Vec<SourceHealth>,
+}
+
+impl FallbackSource {
+    pub fn new(sources: Vec<Box<dyn TemperatureSource>>) -> Self {
+        let health = sources.iter().map(|_| SourceHealth::default()).collect();
+        Self { sources, health }
+    }
+    
+    /// The default CPU temperature chain: hwmon, then thermal zones.
+    #[cfg(not(any(windows, target_os = "macos")))]
+    pub fn cpu() -> Self {
+        Self::new(vec![Box::new(HwmonSource::new()), Box::new(ThermalZoneSource)])
+    }
+    
+    /// The default CPU temperature chain on Windows: LibreHardwareMonitor if
+    /// it is running, then ACPI thermal zones.
+    #[cfg(windows)]
+    pub fn cpu() -> Self {
+        Self::new(vec![Box::new(LibreHardwareMonitorSource::new()), Box::new(AcpiThermalSource::new())])
+    }
+    
+    /// The default CPU temperature chain on macOS: the SMC.
+    #[cfg(target_os = "macos")]
+    pub fn cpu() -> Self {
+        Self::new(vec![Box::new(SmcSource::new())])
+    }
+}
+
+impl TemperatureSource for FallbackSource {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        let mut just_quarantined = vec![false; self.sources.len()];
+        for (i, (source, health)) in self.sources.iter_mut().zip(&mut self.health).enumerate() {
+            if health.quarantined_until.is_some() && !health.is_quarantined() {
+                health.quarantined_until = None;
+                info!("Trying {} again after its quarantine", source.name());
+            }
+            if health.is_quarantined() {
+                continue;
+            }
+            
+            let started = Instant::now();
+            let result = source.read();
+            let latency = started.elapsed();
+            
+            if let Some(time) = health.record(result.is_ok(), latency) {
+                just_quarantined[i] = true;
+                warn!(
+                    "Quarantining {} for {} s: {} of its recent reads failed or took over {} ms",
+                    source.name(),
+                    time.as_secs(),
+                    QUARANTINE_FAILURES,
+                    SLOW_READ.as_millis()
+                );
+            }
+            if let Ok(temp) = result {
+                return Ok(temp);
+            }
+        }
+        
+        // Last resort, without counting against the quarantined sources.
+        // Ones quarantined by this call have just failed, so they are skipped.
+        for (i, (source, health)) in self.sources.iter_mut().zip(&self.health).enumerate() {
+            if health.is_quarantined()
+                && !just_quarantined[i]
+                && let Ok(temp) = source.read()
+            {
+                return Ok(temp);
+            }
+        }
+        
+        anyhow::bail!("Could not read CPU temperature from any source")
+    }
+    
+    fn health(&self) -> Vec<(&str, &SourceHealth)> {
+        self.sources.iter().map(|source| source.name()).zip(&self.health).collect()
+    }
+}
+
+/// Reports the hottest reading among several sources, e.g. CPU and GPU.
+/// Sources that fail are skipped as long as one of them succeeds.
+pub struct HottestSource {
+    sources: Vec<Box<dyn TemperatureSource>>,
+}
+
+impl HottestSource {
+    pub fn new(sources: Vec<Box<dyn TemperatureSource>>) -> Self {
+        Self { sources }
+    }
+}
+
+impl TemperatureSource for HottestSource {
+    fn name(&self) -> &str {
+        "hottest"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        let hottest = self
+            .sources
+            .iter_mut()
+            .filter_map(|source| source.read().ok())
+            .reduce(f32::max);
+        
+        if let Some(temp) = hottest {
+            return Ok(temp);
+        }
+        
+        anyhow::bail!("None of the sources returned a temperature")
+    }
+    
+    fn health(&self) -> Vec<(&str, &SourceHealth)> {
+        self.sources.iter().flat_map(|source| source.health()).collect()
+    }
+}
+
+/// Names the hypervisor when running inside a virtual machine, where the
+/// host's temperature sensors are not visible.
+pub fn detect_hypervisor() -> Option<String> {
+    // Every mainstream x86 hypervisor sets this CPU flag for its guests
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    let virtualized = cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("flags"))
+        .any(|line| line.split_whitespace().any(|flag| flag == "hypervisor"));
+    
+    if !virtualized {
+        return None;
+    }
+    
+    let vendor = fs::read_to_string("/sys/class/dmi/id/sys_vendor")
+        .map(|vendor| vendor.trim().to_string())
+        .unwrap_or_else(|_| "unknown hypervisor".to_string());
+    Some(vendor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const FAST: Duration = Duration::from_millis(1);
+    
+    /// Fails its first `failures` reads, then reads 50°C.
+    struct Flaky {
+        name: &'static str,
+        failures: u32,
+    }
+    
+    impl TemperatureSource for Flaky {
+        fn name(&self) -> &str {
+            self.name
+        }
+        
+        fn read(&mut self) -> Result<f32> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                anyhow::bail!("flaky");
+            }
+            Ok(50.0)
+        }
+    }
+    
+    fn quarantine(health: &mut SourceHealth) -> Option<Duration> {
+        (0..QUARANTINE_FAILURES).filter_map(|_| health.record(false, FAST)).last()
+    }
+    
+    #[test]
+    fn quarantines_after_five_failures_in_the_window() {
+        let mut health = SourceHealth::default();
+        for _ in 1..QUARANTINE_FAILURES {
+            assert_eq!(health.record(false, FAST), None);
+        }
+        for _ in 0..HEALTH_WINDOW - QUARANTINE_FAILURES {
+            assert_eq!(health.record(true, FAST), None);
+        }
+        assert!(!health.is_quarantined());
+        
+        assert_eq!(health.record(false, FAST), Some(QUARANTINE_TIME));
+        assert!(health.is_quarantined());
+    }
+    
+    #[test]
+    fn failures_leaving_the_window_no_longer_count() {
+        let mut health = SourceHealth::default();
+        for _ in 1..QUARANTINE_FAILURES {
+            health.record(false, FAST);
+        }
+        for _ in 0..HEALTH_WINDOW {
+            health.record(true, FAST);
+        }
+        assert_eq!(health.record(false, FAST), None);
+        assert!(!health.is_quarantined());
+    }
+    
+    #[test]
+    fn quarantine_doubles_up_to_the_cap() {
+        let mut health = SourceHealth::default();
+        let times: Vec<u64> = (0..7).map(|_| quarantine(&mut health).unwrap().as_secs()).collect();
+        assert_eq!(times, [60, 120, 240, 480, 900, 900, 900]);
+        
+        // A clean window starts the backoff over
+        for _ in 0..HEALTH_WINDOW {
+            health.record(true, FAST);
+        }
+        assert_eq!(quarantine(&mut health), Some(QUARANTINE_TIME));
+    }
+    
+    #[test]
+    fn slow_reads_count_as_failures() {
+        let mut health = SourceHealth::default();
+        let slow = SLOW_READ + Duration::from_millis(1);
+        for _ in 1..QUARANTINE_FAILURES {
+            assert_eq!(health.record(true, slow), None);
+        }
+        assert!(health.is_degraded());
+        assert_eq!(health.record(true, slow), Some(QUARANTINE_TIME));
+    }
+    
+    #[test]
+    fn quarantined_sources_are_read_as_a_last_resort() {
+        let mut source = FallbackSource::new(vec![
+            Box::new(Flaky { name: "first", failures: QUARANTINE_FAILURES as u32 }),
+            Box::new(Flaky { name: "second", failures: u32::MAX }),
+        ]);
+        for _ in 1..QUARANTINE_FAILURES {
+            assert!(source.read().is_err());
+        }
+        
+        // Both are quarantined by this read and not tried again in it
+        assert!(source.read().is_err());
+        let health = source.health();
+        assert_eq!(health.len(), 2);
+        assert!(health.iter().all(|(_, health)| health.is_quarantined()));
+        
+        // The next read falls back to them, and the first one has recovered
+        assert_eq!(source.read().unwrap(), 50.0);
+        assert_eq!(source.read().unwrap(), 50.0);
+    }
+}
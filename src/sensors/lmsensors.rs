@@ -0,0 +1,297 @@
+//! Labels and ignores from the lm-sensors configuration
+//! (`/etc/sensors3.conf` and `/etc/sensors.d/*`), so inputs go by the names
+//! the `sensors` command shows.
+//!
+//! Only `chip`, `label` and `ignore` are understood; `compute`, `set` and
+//! the rest are skipped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The statements following one `chip` line.
+#[derive(Debug, Clone, Default)]
+struct Section {
+    patterns: Vec<String>,
+    labels: Vec<(String, String)>,
+    ignores: Vec<String>,
+}
+
+impl Section {
+    fn matches(&self, chip: &str) -> bool {
+        self.patterns.iter().any(|pattern| glob(pattern, chip))
+    }
+}
+
+/// Parsed lm-sensors configuration.
+#[derive(Debug, Clone, Default)]
+pub struct SensorsConfig {
+    sections: Vec<Section>,
+}
+
+impl SensorsConfig {
+    /// Reads the system configuration. Missing or unreadable files are
+    /// skipped, like `sensors` does for an empty `sensors.d`.
+    pub fn load() -> Self {
+        let mut paths = vec![PathBuf::from("/etc/sensors3.conf")];
+        if let Ok(entries) = fs::read_dir("/etc/sensors.d") {
+            let mut extra: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+            extra.sort();
+            paths.extend(extra);
+        }
+        
+        let mut config = Self::default();
+        for path in paths {
+            if let Ok(text) = fs::read_to_string(&path) {
+                config.sections.extend(Self::parse(&text).sections);
+            }
+        }
+        config
+    }
+    
+    pub fn parse(text: &str) -> Self {
+        let mut sections = Vec::new();
+        // Statements before the first `chip` line apply to every chip
+        let mut current = Section { patterns: vec!["*".to_string()], ..Section::default() };
+        
+        for line in text.lines() {
+            let tokens = tokens(line);
+            match tokens.as_slice() {
+                [keyword, patterns @ ..] if keyword == "chip" && !patterns.is_empty() => {
+                    sections.push(std::mem::take(&mut current));
+                    current.patterns = patterns.to_vec();
+                }
+                [keyword, feature, label, ..] if keyword == "label" => {
+                    current.labels.push((feature.clone(), label.clone()));
+                }
+                [keyword, feature, ..] if keyword == "ignore" => current.ignores.push(feature.clone()),
+                _ => {}
+            }
+        }
+        sections.push(current);
+        
+        Self { sections }
+    }
+    
+    /// Label configured for `feature` (e.g. `temp1`) of the chip named `chip`
+    /// (see [`chip_name`]). Later statements win, as in lm-sensors.
+    pub fn label(&self, chip: &str, feature: &str) -> Option<&str> {
+        self.sections.iter().rev().filter(|section| section.matches(chip)).find_map(|section| {
+            section.labels.iter().rev().find(|(name, _)| name == feature).map(|(_, label)| label.as_str())
+        })
+    }
+    
+    pub fn is_ignored(&self, chip: &str, feature: &str) -> bool {
+        self.sections
+            .iter()
+            .any(|section| section.matches(chip) && section.ignores.iter().any(|name| name == feature))
+    }
+}
+
+/// Splits a configuration line into words and "quoted strings", dropping
+/// `#` comments.
+fn tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    
+    while let Some(&c) = chars.peek() {
+        if c == '#' {
+            break;
+        }
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        
+        let mut token = String::new();
+        if c == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => token.extend(chars.next()),
+                    c => token.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '#' {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+        }
+        tokens.push(token);
+    }
+    
+    tokens
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters.
+fn glob(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len()).filter(|&i| text.is_char_boundary(i)).any(|i| glob(rest, &text[i..]))
+        }
+    }
+}
+
+/// The name lm-sensors gives a hwmon chip, e.g. `k10temp-pci-00c3` or
+/// `coretemp-isa-0000`, which `chip` lines are matched against.
+pub fn chip_name(hwmon: &Path) -> Option<String> {
+    let name = fs::read_to_string(hwmon.join("name")).ok()?;
+    let name = name.trim();
+    
+    let device = hwmon.join("device");
+    let id = fs::read_link(&device).ok().and_then(|target| Some(target.file_name()?.to_string_lossy().into_owned()));
+    let subsystem = fs::read_link(device.join("subsystem"))
+        .ok()
+        .and_then(|target| Some(target.file_name()?.to_string_lossy().into_owned()));
+    
+    let bus = match (subsystem.as_deref(), id) {
+        // 0000:00:18.3 -> (domain << 16) + (bus << 8) + (device << 3) + function
+        (Some("pci"), Some(id)) => {
+            let mut parts = id.split([':', '.']).map(|part| u32::from_str_radix(part, 16));
+            let (Some(Ok(domain)), Some(Ok(bus)), Some(Ok(slot)), Some(Ok(function))) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Some(format!("{}-pci-0000", name));
+            };
+            format!("pci-{:04x}", (domain << 16) + (bus << 8) + (slot << 3) + function)
+        }
+        // coretemp.0, nct6775.656 -> the platform id, in hex
+        (Some("platform"), Some(id)) => {
+            let address = id.rsplit_once('.').and_then(|(_, number)| number.parse::<u32>().ok()).unwrap_or(0);
+            format!("isa-{:04x}", address)
+        }
+        // 3-004c -> bus 3, address 0x4c
+        (Some("i2c"), Some(id)) => {
+            let (bus, address) = id.split_once('-').unwrap_or(("0", "0"));
+            format!("i2c-{}-{:x}", bus, u32::from_str_radix(address, 16).unwrap_or(0))
+        }
+        (Some("acpi"), _) => "acpi-0".to_string(),
+        _ => "virtual-0".to_string(),
+    };
+    
+    Some(format!("{}-{}", name, bus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const CONFIG: &str = r#"
+# Applies to every chip
+label temp1 "Generic"
+
+chip "k10temp-pci-*"
+    label temp1 "CPU Tctl"   # as shown by sensors
+    label temp3 "CCD \"1\""
+    ignore temp2
+
+chip "nct6775-*" "nct6798-*"
+    label temp1 Board
+    compute temp1 @*2, @/2
+
+chip "k10temp-pci-00c3"
+    label temp1 "CPU"
+"#;
+    
+    #[test]
+    fn labels_go_by_the_last_matching_statement() {
+        let config = SensorsConfig::parse(CONFIG);
+        
+        assert_eq!(config.label("k10temp-pci-00c3", "temp1"), Some("CPU"));
+        assert_eq!(config.label("k10temp-pci-00cb", "temp1"), Some("CPU Tctl"));
+        assert_eq!(config.label("k10temp-pci-00cb", "temp3"), Some(r#"CCD "1""#));
+        assert_eq!(config.label("nct6798-isa-0290", "temp1"), Some("Board"));
+        assert_eq!(config.label("coretemp-isa-0000", "temp1"), Some("Generic"));
+        assert_eq!(config.label("coretemp-isa-0000", "temp2"), None);
+    }
+    
+    #[test]
+    fn ignores_only_apply_to_matching_chips() {
+        let config = SensorsConfig::parse(CONFIG);
+        
+        assert!(config.is_ignored("k10temp-pci-00c3", "temp2"));
+        assert!(!config.is_ignored("k10temp-pci-00c3", "temp1"));
+        assert!(!config.is_ignored("nct6775-isa-0290", "temp2"));
+        assert!(!SensorsConfig::default().is_ignored("k10temp-pci-00c3", "temp2"));
+    }
+    
+    #[test]
+    fn lines_split_into_words_and_quoted_strings() {
+        assert_eq!(tokens(r#"label temp1 "CPU Tctl""#), ["label", "temp1", "CPU Tctl"]);
+        assert_eq!(tokens(r#"  label  temp1 "a \"b\" \\ c"  "#), ["label", "temp1", r#"a "b" \ c"#]);
+        assert_eq!(tokens("ignore temp2# trailing comment"), ["ignore", "temp2"]);
+        assert_eq!(tokens(r##"label temp1 "# not a comment""##), ["label", "temp1", "# not a comment"]);
+        assert_eq!(tokens(r#"label temp1 "unterminated"#), ["label", "temp1", "unterminated"]);
+        assert!(tokens("   # only a comment").is_empty());
+    }
+    
+    #[test]
+    fn globs_match_any_run_of_characters() {
+        assert!(glob("k10temp-pci-00c3", "k10temp-pci-00c3"));
+        assert!(!glob("k10temp-pci-00c3", "k10temp-pci-00cb"));
+        assert!(glob("k10temp-*", "k10temp-pci-00c3"));
+        assert!(glob("*-isa-*", "coretemp-isa-0000"));
+        assert!(glob("*", ""));
+        assert!(glob("nct*-isa-0290", "nct6798-isa-0290"));
+        assert!(!glob("nct*-isa-0290", "nct6798-isa-0a20"));
+        assert!(!glob("*-pci-*", "coretemp-isa-0000"));
+        assert!(glob("°*", "°C"));
+    }
+    
+    /// A hwmon directory named `name` whose device is `id` on `subsystem`,
+    /// laid out like sysfs with symlinks.
+    #[cfg(unix)]
+    fn fake_hwmon(root: &Path, name: &str, device: Option<(&str, &str)>) -> PathBuf {
+        use std::os::unix::fs::symlink;
+        
+        let hwmon = root.join(format!("hwmon-{}", name));
+        fs::create_dir_all(&hwmon).unwrap();
+        fs::write(hwmon.join("name"), format!("{}\n", name)).unwrap();
+        
+        if let Some((subsystem, id)) = device {
+            let bus = root.join("bus").join(subsystem);
+            let device = root.join("devices").join(id);
+            fs::create_dir_all(&bus).unwrap();
+            fs::create_dir_all(&device).unwrap();
+            symlink(&bus, device.join("subsystem")).unwrap();
+            symlink(&device, hwmon.join("device")).unwrap();
+        }
+        hwmon
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn chip_names_encode_the_bus_address() {
+        let root = std::env::temp_dir().join(format!("risemode-lmsensors-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        
+        let cases = [
+            ("k10temp", Some(("pci", "0000:00:18.3")), "k10temp-pci-00c3"),
+            ("amdgpu", Some(("pci", "0001:03:00.0")), "amdgpu-pci-10300"),
+            ("coretemp", Some(("platform", "coretemp.0")), "coretemp-isa-0000"),
+            ("nct6775", Some(("platform", "nct6775.656")), "nct6775-isa-0290"),
+            ("lm75", Some(("i2c", "3-004c")), "lm75-i2c-3-4c"),
+            ("acpitz", Some(("acpi", "LNXTHERM:00")), "acpitz-acpi-0"),
+            ("nvme", None, "nvme-virtual-0"),
+        ];
+        let names: Vec<_> = cases
+            .iter()
+            .map(|&(name, device, _)| chip_name(&fake_hwmon(&root, name, device)))
+            .collect();
+        let missing = chip_name(&root.join("hwmon-missing"));
+        let _ = fs::remove_dir_all(&root);
+        
+        for ((_, _, expected), name) in cases.iter().zip(names) {
+            assert_eq!(name.as_deref(), Some(*expected));
+        }
+        assert_eq!(missing, None);
+    }
+}
@@ -0,0 +1,200 @@
+//! macOS temperature sources, read from the System Management Controller.
+
+use anyhow::{Context, Result};
+use std::ffi::{c_char, c_void};
+use std::mem;
+
+use super::TemperatureSource;
+
+type MachPort = u32;
+type IoObject = u32;
+type KernReturn = i32;
+
+const KERN_SUCCESS: KernReturn = 0;
+const K_IO_MAIN_PORT_DEFAULT: MachPort = 0;
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    static mach_task_self_: MachPort;
+    
+    fn IOServiceMatching(name: *const c_char) -> *mut c_void;
+    fn IOServiceGetMatchingService(main_port: MachPort, matching: *mut c_void) -> IoObject;
+    fn IOServiceOpen(service: IoObject, owning_task: MachPort, kind: u32, connect: *mut IoObject) -> KernReturn;
+    fn IOServiceClose(connect: IoObject) -> KernReturn;
+    fn IOObjectRelease(object: IoObject) -> KernReturn;
+    fn IOConnectCallStructMethod(
+        connection: IoObject,
+        selector: u32,
+        input: *const c_void,
+        input_size: usize,
+        output: *mut c_void,
+        output_size: *mut usize,
+    ) -> KernReturn;
+}
+
+// AppleSMC user client interface, as used by smcFanControl and friends
+const KERNEL_INDEX_SMC: u32 = 2;
+const SMC_CMD_READ_BYTES: u8 = 5;
+const SMC_CMD_READ_KEYINFO: u8 = 9;
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct SmcVersion {
+    major: u8,
+    minor: u8,
+    build: u8,
+    reserved: u8,
+    release: u16,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct SmcPowerLimits {
+    version: u16,
+    length: u16,
+    cpu_limit: u32,
+    gpu_limit: u32,
+    mem_limit: u32,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct SmcKeyInfo {
+    data_size: u32,
+    data_type: u32,
+    data_attributes: u8,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct SmcKeyData {
+    key: u32,
+    vers: SmcVersion,
+    power_limits: SmcPowerLimits,
+    key_info: SmcKeyInfo,
+    result: u8,
+    status: u8,
+    data8: u8,
+    data32: u32,
+    bytes: [u8; 32],
+}
+
+// The kernel rejects calls whose buffers aren't exactly this size
+const _: () = assert!(mem::size_of::<SmcKeyData>() == 80);
+
+/// Keys tried in order: CPU proximity, die, then the filtered PECI die
+/// reading, as exposed by Intel Macs and by VirtualSMC/FakeSMC on Hackintoshes.
+const CPU_KEYS: [&[u8; 4]; 3] = [b"TC0P", b"TC0D", b"TC0E"];
+
+/// CPU temperature from the SMC (`TC0P`/`TC0D`/`TC0E`), the only sensor macOS
+/// exposes without a kernel extension.
+#[derive(Default)]
+pub struct SmcSource {
+    connection: Option<IoObject>,
+}
+
+impl SmcSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    fn connect(&mut self) -> Result<IoObject> {
+        if let Some(connection) = self.connection {
+            return Ok(connection);
+        }
+        
+        // SAFETY: plain IOKit calls; the matching dictionary is consumed by
+        // IOServiceGetMatchingService and the service is released after opening
+        let connection = unsafe {
+            let service = IOServiceGetMatchingService(K_IO_MAIN_PORT_DEFAULT, IOServiceMatching(c"AppleSMC".as_ptr()));
+            if service == 0 {
+                anyhow::bail!("AppleSMC service not found");
+            }
+            
+            let mut connection = 0;
+            let result = IOServiceOpen(service, mach_task_self_, 0, &mut connection);
+            IOObjectRelease(service);
+            if result != KERN_SUCCESS {
+                anyhow::bail!("Failed to open AppleSMC (kern_return {:#x})", result);
+            }
+            connection
+        };
+        
+        self.connection = Some(connection);
+        Ok(connection)
+    }
+    
+    fn call(&self, connection: IoObject, input: &SmcKeyData) -> Result<SmcKeyData> {
+        let mut output = SmcKeyData::default();
+        let mut output_size = mem::size_of::<SmcKeyData>();
+        
+        // SAFETY: both buffers are SmcKeyData, which matches the kernel's layout
+        let result = unsafe {
+            IOConnectCallStructMethod(
+                connection,
+                KERNEL_INDEX_SMC,
+                (input as *const SmcKeyData).cast(),
+                mem::size_of::<SmcKeyData>(),
+                (&mut output as *mut SmcKeyData).cast(),
+                &mut output_size,
+            )
+        };
+        if result != KERN_SUCCESS || output.result != 0 {
+            anyhow::bail!("SMC call failed (kern_return {:#x}, result {})", result, output.result);
+        }
+        
+        Ok(output)
+    }
+    
+    /// Reads a temperature key, decoding the `sp78` (Intel) and `flt ` (Apple
+    /// silicon) data types.
+    fn read_key(&mut self, key: &[u8; 4]) -> Result<f32> {
+        let connection = self.connect()?;
+        
+        let mut input = SmcKeyData { key: u32::from_be_bytes(*key), data8: SMC_CMD_READ_KEYINFO, ..Default::default() };
+        let info = self.call(connection, &input)?.key_info;
+        
+        input.key_info.data_size = info.data_size;
+        input.data8 = SMC_CMD_READ_BYTES;
+        let bytes = self.call(connection, &input)?.bytes;
+        
+        match &info.data_type.to_be_bytes() {
+            b"sp78" => Ok(i16::from_be_bytes([bytes[0], bytes[1]]) as f32 / 256.0),
+            b"flt " => Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            other => anyhow::bail!("Unsupported SMC data type {:?}", String::from_utf8_lossy(other)),
+        }
+    }
+}
+
+impl TemperatureSource for SmcSource {
+    fn name(&self) -> &str {
+        "smc"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        let mut last_error = None;
+        
+        for key in CPU_KEYS {
+            match self.read_key(key) {
+                // Missing keys read back as zero on some SMC emulators
+                Ok(temp) if temp > 0.0 => return Ok(temp),
+                Ok(_) => {}
+                Err(e) => last_error = Some(e),
+            }
+        }
+        
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("SMC reports no CPU temperature")))
+            .context("Could not read the CPU temperature from the SMC")
+    }
+}
+
+impl Drop for SmcSource {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            // SAFETY: the connection was opened by IOServiceOpen and is closed once
+            unsafe {
+                IOServiceClose(connection);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Windows temperature sources, read through WMI.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use wmi::{COMLibrary, WMIConnection};
+
+use super::TemperatureSource;
+
+/// Opens (or reuses) a WMI connection to `namespace`. A failed query drops
+/// the connection so the next read starts from scratch, e.g. after
+/// LibreHardwareMonitor was restarted.
+fn connect<'a>(connection: &'a mut Option<WMIConnection>, namespace: &str) -> Result<&'a WMIConnection> {
+    if connection.is_none() {
+        let com = COMLibrary::new().context("Failed to initialize COM")?;
+        let wmi = WMIConnection::with_namespace_path(namespace, com)
+            .with_context(|| format!("Failed to connect to WMI namespace {}", namespace))?;
+        *connection = Some(wmi);
+    }
+    
+    Ok(connection.as_ref().unwrap())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ThermalZone {
+    current_temperature: u32,
+}
+
+/// ACPI thermal zones from WMI (`MSAcpi_ThermalZoneTemperature`), the
+/// Windows counterpart of [`ThermalZoneSource`](super::ThermalZoneSource).
+///
+/// Needs administrator rights, and many boards report a fixed or
+/// motherboard value here rather than the CPU package.
+#[derive(Default)]
+pub struct AcpiThermalSource {
+    connection: Option<WMIConnection>,
+}
+
+impl AcpiThermalSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TemperatureSource for AcpiThermalSource {
+    fn name(&self) -> &str {
+        "acpi"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        let wmi = connect(&mut self.connection, "root\\WMI")?;
+        let zones: Vec<ThermalZone> = match wmi.raw_query("SELECT CurrentTemperature FROM MSAcpi_ThermalZoneTemperature") {
+            Ok(zones) => zones,
+            Err(e) => {
+                self.connection = None;
+                return Err(e).context("WMI query for MSAcpi_ThermalZoneTemperature failed");
+            }
+        };
+        
+        // Reported in tenths of a kelvin
+        zones
+            .iter()
+            .map(|zone| zone.current_temperature as f32 / 10.0 - 273.15)
+            .reduce(f32::max)
+            .context("No ACPI thermal zone reported a temperature")
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Sensor {
+    identifier: String,
+    value: f32,
+}
+
+/// CPU temperature published by a running LibreHardwareMonitor, which
+/// exposes its sensors in the `root\LibreHardwareMonitor` WMI namespace.
+///
+/// Reports the hottest CPU temperature sensor (package or per-core).
+#[derive(Default)]
+pub struct LibreHardwareMonitorSource {
+    connection: Option<WMIConnection>,
+}
+
+impl LibreHardwareMonitorSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TemperatureSource for LibreHardwareMonitorSource {
+    fn name(&self) -> &str {
+        "librehardwaremonitor"
+    }
+    
+    fn read(&mut self) -> Result<f32> {
+        let wmi = connect(&mut self.connection, "root\\LibreHardwareMonitor")?;
+        let sensors: Vec<Sensor> =
+            match wmi.raw_query("SELECT Identifier, Value FROM Sensor WHERE SensorType = 'Temperature'") {
+                Ok(sensors) => sensors,
+                Err(e) => {
+                    self.connection = None;
+                    return Err(e).context("WMI query for LibreHardwareMonitor sensors failed");
+                }
+            };
+        
+        // Identifiers look like /intelcpu/0/temperature/0 or /amdcpu/0/temperature/2
+        sensors
+            .iter()
+            .filter(|sensor| sensor.identifier.starts_with("/intelcpu/") || sensor.identifier.starts_with("/amdcpu/"))
+            .map(|sensor| sensor.value)
+            .reduce(f32::max)
+            .context("LibreHardwareMonitor reports no CPU temperature")
+    }
+}
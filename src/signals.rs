@@ -0,0 +1,56 @@
+//! SIGTERM/SIGINT and SIGHUP handling for the running driver.
+//!
+//! The handlers only set flags; the send loop checks them once per tick, so
+//! a stop takes effect within one `--interval`.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STOP: AtomicBool = AtomicBool::new(false);
+static RELOAD: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_os = "linux")]
+extern "C" fn handle(signal: libc::c_int) {
+    if signal == libc::SIGHUP {
+        RELOAD.store(true, Ordering::SeqCst);
+    } else {
+        STOP.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Installs the handlers for SIGTERM, SIGINT and SIGHUP.
+#[cfg(target_os = "linux")]
+pub fn install() -> io::Result<()> {
+    for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP] {
+        // SAFETY: the action is fully initialized and the handler only
+        // touches atomics, which is async-signal-safe
+        let result = unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, std::ptr::null_mut())
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    
+    Ok(())
+}
+
+/// Other platforms keep the default handlers, which end the process.
+#[cfg(not(target_os = "linux"))]
+pub fn install() -> io::Result<()> {
+    Ok(())
+}
+
+/// Whether SIGTERM or SIGINT was received.
+pub fn stop_requested() -> bool {
+    STOP.load(Ordering::SeqCst)
+}
+
+/// Whether SIGHUP was received since the last call.
+pub fn take_reload() -> bool {
+    RELOAD.swap(false, Ordering::SeqCst)
+}
@@ -0,0 +1,224 @@
+//! `risemode-driver soak`: drives the display at a fixed frame rate for
+//! hours and logs every write error and disconnect, to find out how fast
+//! the firmware can safely be updated.
+
+use anyhow::Result;
+use hidapi::HidApi;
+use risemode_driver::protocol::Frame;
+use risemode_driver::{HotplugEvent, HotplugMonitor, RisemodeDevice};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli::Options;
+use crate::recovery;
+use crate::signals;
+
+/// How often a progress line is printed.
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+/// Wait after a failed reconnect, doubled on each further failure.
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+struct Stats {
+    frames: u64,
+    write_errors: u64,
+    reconnects: u64,
+    disconnects: u64,
+}
+
+/// When to try reopening the displays after they were lost.
+#[derive(Debug)]
+struct Reconnect {
+    retry_at: Option<Instant>,
+    backoff: Duration,
+}
+
+impl Reconnect {
+    fn new() -> Self {
+        Self { retry_at: None, backoff: FIRST_RETRY }
+    }
+    
+    /// Notes that the displays were lost, so the next [`poll`](Self::poll) reopens them.
+    fn lost(&mut self, now: Instant) {
+        self.retry_at = Some(now);
+        self.backoff = FIRST_RETRY;
+    }
+    
+    /// Calls `open` if a retry is due at `now`, backing off further when it
+    /// fails. `None` means nothing was tried.
+    fn poll(
+        &mut self,
+        now: Instant,
+        open: impl FnOnce() -> Result<Vec<RisemodeDevice>>,
+    ) -> Option<Result<Vec<RisemodeDevice>>> {
+        if self.retry_at.is_none_or(|retry_at| now < retry_at) {
+            return None;
+        }
+        
+        let result = open().and_then(|devices| {
+            if devices.is_empty() {
+                anyhow::bail!("no display was opened");
+            }
+            Ok(devices)
+        });
+        if result.is_ok() {
+            self.retry_at = None;
+        } else {
+            self.retry_at = Some(now + self.backoff);
+            self.backoff = (self.backoff * 2).min(MAX_RETRY);
+        }
+        Some(result)
+    }
+}
+
+fn elapsed(started: Instant) -> String {
+    let secs = started.elapsed().as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+/// Sends `rate` frames per second for `hours`, or until SIGINT/SIGTERM.
+pub fn run(api: &mut HidApi, options: &Options, rate: f64, hours: f64) -> Result<()> {
+    let period = Duration::from_secs_f64(1.0 / rate);
+    let duration = Duration::from_secs_f64(hours * 3600.0);
+    let hotplug = HotplugMonitor::new(options.vendor_id(), options.product_id()).ok();
+    if let Err(e) = signals::install() {
+        tracing::warn!("Could not install signal handlers: {}", e);
+    }
+    
+    let mut devices: Vec<RisemodeDevice> = crate::open_devices(api, options)?;
+    println!(
+        "Soaking {} display(s) at {:.1} Hz for {:.1} h, Ctrl+C stops early",
+        devices.len(),
+        rate,
+        hours
+    );
+    
+    let mut stats = Stats::default();
+    let started = Instant::now();
+    let mut last_report = started;
+    let mut next_frame = started;
+    let mut reconnect = Reconnect::new();
+    
+    while started.elapsed() < duration && !signals::stop_requested() {
+        while let Some(event) = hotplug.as_ref().and_then(|hotplug| hotplug.wait(Duration::ZERO).ok().flatten()) {
+            if event == HotplugEvent::Removed {
+                stats.disconnects += 1;
+                println!("[{}] Display disconnected", elapsed(started));
+            }
+        }
+        
+        // Sweep the whole range so every digit segment gets exercised
+        let temp = (stats.frames % 1000) as f32 / 10.0;
+        let frame = Frame::temperature(temp)?;
+        let mut failed = false;
+        for device in &devices {
+            match device.send(&frame) {
+                Ok(()) => stats.frames += 1,
+                Err(e) => {
+                    stats.write_errors += 1;
+                    eprintln!("[{}] Write error after {} frames: {:#}", elapsed(started), stats.frames, e);
+                    failed = true;
+                }
+            }
+        }
+        
+        if failed {
+            devices.clear();
+            thread::sleep(recovery::HOTPLUG_SETTLE);
+            reconnect.lost(Instant::now());
+        }
+        
+        let reopened = reconnect.poll(Instant::now(), || {
+            recovery::refresh(api);
+            crate::open_devices(api, options)
+        });
+        match reopened {
+            Some(Ok(reopened)) => {
+                devices = reopened;
+                stats.reconnects += 1;
+                println!("[{}] Reconnected", elapsed(started));
+                next_frame = Instant::now();
+            }
+            Some(Err(e)) => eprintln!("[{}] Reconnect failed: {:#}", elapsed(started), e),
+            None => {}
+        }
+        
+        if last_report.elapsed() >= REPORT_INTERVAL {
+            last_report = Instant::now();
+            println!(
+                "[{}] {} frames, {} write error(s), {} reconnect(s), {} disconnect(s)",
+                elapsed(started),
+                stats.frames,
+                stats.write_errors,
+                stats.reconnects,
+                stats.disconnects
+            );
+        }
+        
+        next_frame += period;
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            // Running behind; don't burst to catch up
+            next_frame = now;
+        }
+    }
+    
+    let secs = started.elapsed().as_secs_f64();
+    println!(
+        "Soak finished after {}: {} frames ({:.1}/s), {} write error(s), {} reconnect(s), {} disconnect(s)",
+        elapsed(started),
+        stats.frames,
+        stats.frames as f64 / secs,
+        stats.write_errors,
+        stats.reconnects,
+        stats.disconnects
+    );
+    
+    if stats.write_errors > 0 || stats.disconnects > 0 {
+        anyhow::bail!("the display did not keep up with {:.1} Hz", rate);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risemode_driver::testing::{MockTransport, TestDevice};
+    
+    #[test]
+    fn reconnects_after_a_failed_attempt() {
+        let mock = MockTransport::new();
+        let start = Instant::now();
+        let mut reconnect = Reconnect::new();
+        assert!(reconnect.poll(start, || unreachable!("nothing was lost")).is_none());
+        
+        reconnect.lost(start);
+        let first = reconnect.poll(start, || anyhow::bail!("still unplugged"));
+        assert!(matches!(first, Some(Err(_))));
+        assert!(reconnect.poll(start + FIRST_RETRY / 2, || unreachable!("backing off")).is_none());
+        
+        let devices = reconnect.poll(start + FIRST_RETRY, || Ok(vec![mock.device()])).unwrap().unwrap();
+        devices[0].send(&Frame::temperature(42.5).unwrap()).unwrap();
+        assert_eq!(mock.reports()[0][..2], [42, 5]);
+        assert!(reconnect.poll(start + MAX_RETRY, || unreachable!("already reconnected")).is_none());
+    }
+    
+    #[test]
+    fn retries_back_off_up_to_the_limit() {
+        let mut now = Instant::now();
+        let mut reconnect = Reconnect::new();
+        reconnect.lost(now);
+        
+        let mut waits = Vec::new();
+        for _ in 0..7 {
+            assert!(reconnect.poll(now, || Ok(Vec::new())).unwrap().is_err());
+            let retry_at = reconnect.retry_at.unwrap();
+            waits.push((retry_at - now).as_secs());
+            now = retry_at;
+        }
+        assert_eq!(waits, [1, 2, 4, 8, 16, 30, 30]);
+    }
+}
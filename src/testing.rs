@@ -0,0 +1,170 @@
+//! Helpers for testing code that drives the display, without the hardware.
+//!
+//! Enabled by the `test-util` feature. [`MockTransport`] stands in for the
+//! USB link and records what is written; anything implementing [`TestDevice`]
+//! can be checked against a golden file with [`assert_golden`].
+//!
+//! ```
+//! use risemode_driver::protocol::Frame;
+//! use risemode_driver::testing::{MockTransport, TestDevice};
+//!
+//! let mock = MockTransport::new();
+//! let device = mock.device();
+//! device.send(&Frame::temperature(42.5).unwrap()).unwrap();
+//! assert_eq!(mock.reports()[0][..2], [42, 5]);
+//! ```
+
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::transport::HidTransport;
+use crate::RisemodeDevice;
+
+/// Set to rewrite golden files from the current output instead of comparing.
+pub const UPDATE_GOLDEN_VAR: &str = "UPDATE_GOLDEN";
+
+/// A stand-in for the display that keeps what it was sent.
+///
+/// Implement it for your own transport to use [`assert_golden`] with it.
+/// Devices from [`TestDevice::device`] share the capture, so keep the
+/// `TestDevice` to inspect what they have written.
+pub trait TestDevice: HidTransport + Clone + Send + 'static {
+    /// Every report written so far, oldest first.
+    fn reports(&self) -> Vec<Vec<u8>>;
+    
+    /// A device writing to this capture.
+    fn device(&self) -> RisemodeDevice {
+        RisemodeDevice::with_transport(self.clone(), "/dev/hidraw-test")
+    }
+}
+
+/// Records every report in memory instead of sending it.
+///
+/// Clones share the recording, so keep one to inspect what a
+/// [`RisemodeDevice`](crate::RisemodeDevice) built on another has written.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    reports: Arc<Mutex<Vec<Vec<u8>>>>,
+    incoming: Arc<Mutex<VecDeque<Vec<u8>>>>,
+    failing: Arc<AtomicBool>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Queues a report for the next read, as if the device had sent it.
+    pub fn push_incoming(&self, report: &[u8]) {
+        self.incoming.lock().unwrap().push_back(report.to_vec());
+    }
+    
+    /// Makes the following writes fail, like an unplugged display.
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+}
+
+impl HidTransport for MockTransport {
+    fn write(&self, data: &[u8]) -> Result<usize> {
+        if self.failing.load(Ordering::SeqCst) {
+            anyhow::bail!("mock transport is failing");
+        }
+        
+        self.reports.lock().unwrap().push(data.to_vec());
+        Ok(data.len())
+    }
+    
+    fn read_timeout(&self, buf: &mut [u8], _timeout_ms: i32) -> Result<usize> {
+        let Some(report) = self.incoming.lock().unwrap().pop_front() else {
+            return Ok(0);
+        };
+        
+        let len = report.len().min(buf.len());
+        buf[..len].copy_from_slice(&report[..len]);
+        Ok(len)
+    }
+}
+
+impl TestDevice for MockTransport {
+    fn reports(&self) -> Vec<Vec<u8>> {
+        self.reports.lock().unwrap().clone()
+    }
+}
+
+/// Compares what `capture` was sent with the golden file at `path`: one
+/// report per line as space-separated hex, blank lines and `#` comments
+/// ignored. With `UPDATE_GOLDEN=1` set the file is written instead, keeping
+/// the `#` comments at its top.
+///
+/// # Panics
+///
+/// When the reports differ from the file, naming the first difference, or
+/// when the file can't be read or written.
+pub fn assert_golden(capture: &impl TestDevice, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let actual: Vec<String> = capture.reports().iter().map(|report| hex(report)).collect();
+    
+    if env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+        let existing = fs::read_to_string(path).unwrap_or_default();
+        if let Err(e) = write_golden(path, &golden_text(&existing, &actual)) {
+            panic!("{:#}", e);
+        }
+        return;
+    }
+    
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        panic!("Failed to read {} ({}); run with {}=1 to create it", path.display(), e, UPDATE_GOLDEN_VAR)
+    });
+    let expected: Vec<&str> =
+        text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).collect();
+    
+    for (index, (expected, actual)) in expected.iter().zip(&actual).enumerate() {
+        assert_eq!(expected, actual, "report {} differs from {}", index + 1, path.display());
+    }
+    assert_eq!(
+        expected.len(),
+        actual.len(),
+        "{} holds {} report(s) but {} were written",
+        path.display(),
+        expected.len(),
+        actual.len()
+    );
+}
+
+/// `reports` one per line, after the leading comment lines of `existing`.
+fn golden_text(existing: &str, reports: &[String]) -> String {
+    let header = existing.lines().take_while(|line| line.trim_start().starts_with('#'));
+    header.chain(reports.iter().map(String::as_str)).map(|line| format!("{}\n", line)).collect()
+}
+
+fn write_golden(path: &Path, text: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn hex(report: &[u8]) -> String {
+    report.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn regenerated_golden_files_keep_their_header() {
+        let reports = ["2d 03".to_string(), "00 00".to_string()];
+        let existing = "# Frames for 45.3 °C\n  # and a blank one\n15 04\n# not part of the header\n";
+        
+        assert_eq!(golden_text(existing, &reports), "# Frames for 45.3 °C\n  # and a blank one\n2d 03\n00 00\n");
+        assert_eq!(golden_text("", &reports), "2d 03\n00 00\n");
+        assert_eq!(golden_text("15 04\n# late comment\n", &reports), "2d 03\n00 00\n");
+    }
+}
@@ -0,0 +1,229 @@
+//! `--textfile`: the readings and counters in the Prometheus text format, for
+//! node_exporter's textfile collector.
+//!
+//! The file is rewritten every tick through a temporary file and a rename, so
+//! the collector never reads half of it. The temporary name doesn't end in
+//! `.prom`, so the collector skips it.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::usage::Counters;
+
+pub struct Textfile {
+    path: PathBuf,
+    /// Latest value per (display, metric) label pair, kept while rotating
+    temperatures: BTreeMap<(String, String), f32>,
+    percentages: BTreeMap<(String, String), f32>,
+    state: String,
+    failing: bool,
+}
+
+impl Textfile {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            temperatures: BTreeMap::new(),
+            percentages: BTreeMap::new(),
+            state: String::new(),
+            failing: false,
+        }
+    }
+    
+    /// The directory the sandbox has to leave writable.
+    pub fn dir(path: &Path) -> &Path {
+        path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."))
+    }
+    
+    /// Records a reading shown on `display` (empty for the default displays).
+    /// Temperatures are in °C whatever `--unit` says, as Prometheus expects.
+    pub fn reading(&mut self, display: &str, metric: &str, value: f32, temperature: bool) {
+        let readings = if temperature { &mut self.temperatures } else { &mut self.percentages };
+        readings.insert((display.to_string(), metric.to_string()), value);
+    }
+    
+    /// Drops the readings of `display`, e.g. once it was unplugged, so the
+    /// file doesn't keep reporting its last value.
+    pub fn forget(&mut self, display: &str) {
+        self.temperatures.retain(|(name, _), _| name != display);
+        self.percentages.retain(|(name, _), _| name != display);
+    }
+    
+    pub fn state(&mut self, state: &str) {
+        self.state = state.to_string();
+    }
+    
+    /// Writes the file; failures are logged once until a write succeeds again.
+    /// Without any display connected, no readings are reported.
+    pub fn write(&mut self, counters: &Counters, displays: usize) {
+        if displays == 0 {
+            self.temperatures.clear();
+            self.percentages.clear();
+        }
+        
+        match self.write_atomically(&self.render(counters, displays)) {
+            Ok(()) => self.failing = false,
+            Err(e) => {
+                if !self.failing {
+                    warn!("Could not write the metrics file: {:#}", e);
+                }
+                self.failing = true;
+            }
+        }
+    }
+    
+    fn render(&self, counters: &Counters, displays: usize) -> String {
+        let mut text = String::new();
+        
+        gauge(&mut text, "risemode_temperature_celsius", "Temperature shown on a display");
+        for ((display, metric), value) in &self.temperatures {
+            let _ = writeln!(text, "risemode_temperature_celsius{} {}", labels(display, metric), value);
+        }
+        gauge(&mut text, "risemode_usage_percent", "CPU load or memory use shown on a display");
+        for ((display, metric), value) in &self.percentages {
+            let _ = writeln!(text, "risemode_usage_percent{} {}", labels(display, metric), value);
+        }
+        
+        gauge(&mut text, "risemode_displays_connected", "Displays the driver is sending to");
+        let _ = writeln!(text, "risemode_displays_connected {}", displays);
+        gauge(&mut text, "risemode_state", "What the driver is doing, 1 for the current state");
+        let _ = writeln!(text, "risemode_state{{state=\"{}\"}} 1", escape(&self.state));
+        gauge(&mut text, "risemode_last_update_timestamp_seconds", "When this file was written");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let _ = writeln!(text, "risemode_last_update_timestamp_seconds {:.3}", now.as_secs_f64());
+        
+        // Totals across runs, as shown by `risemode-driver info`
+        counter(&mut text, "risemode_frames_sent_total", "Frames written to displays", counters.frames_sent);
+        counter(&mut text, "risemode_sensor_failures_total", "Failed sensor reads", counters.sensor_failures);
+        let connects = counters.connects;
+        counter(&mut text, "risemode_connects_total", "Times the driver connected to the displays", connects);
+        counter(&mut text, "risemode_reopens_total", "Recoveries by reopening a display", counters.reopens);
+        let reenumerations = counters.reenumerations;
+        counter(&mut text, "risemode_reenumerations_total", "Recoveries by re-enumerating HID devices", reenumerations);
+        let usb_resets = counters.usb_resets;
+        counter(&mut text, "risemode_usb_resets_total", "Recoveries by resetting a display over USB", usb_resets);
+        let reports = counters.reports_received;
+        counter(&mut text, "risemode_reports_received_total", "Reports the displays sent back", reports);
+        
+        text
+    }
+    
+    fn write_atomically(&self, text: &str) -> Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        
+        fs::write(&tmp, text).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path).with_context(|| format!("Failed to replace {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+fn gauge(text: &mut String, name: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} gauge", name);
+}
+
+/// A counter and its value. `name` ends in `_total`, and the TYPE line has
+/// to name it the same way as the sample.
+fn counter(text: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} counter", name);
+    let _ = writeln!(text, "{} {}", name, value);
+}
+
+fn labels(display: &str, metric: &str) -> String {
+    format!("{{display=\"{}\",metric=\"{}\"}}", escape(display), escape(metric))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn textfile() -> Textfile {
+        let mut textfile = Textfile::new(Path::new("risemode.prom"));
+        textfile.state("streaming");
+        textfile.reading("", "temperature", 45.5, true);
+        textfile.reading("top", "gpu-temperature", 61.0, true);
+        textfile.reading("", "load", 12.5, false);
+        textfile
+    }
+    
+    /// The lines of `text` without the timestamp, which changes every call.
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines().filter(|line| !line.starts_with("risemode_last_update_timestamp_seconds ")).collect()
+    }
+    
+    #[test]
+    fn render_writes_readings_and_counters() {
+        let counters = Counters { frames_sent: 120, reopens: 2, ..Counters::default() };
+        let text = textfile().render(&counters, 2);
+        let lines = lines(&text);
+        
+        for expected in [
+            "# TYPE risemode_temperature_celsius gauge",
+            r#"risemode_temperature_celsius{display="",metric="temperature"} 45.5"#,
+            r#"risemode_temperature_celsius{display="top",metric="gpu-temperature"} 61"#,
+            r#"risemode_usage_percent{display="",metric="load"} 12.5"#,
+            "risemode_displays_connected 2",
+            r#"risemode_state{state="streaming"} 1"#,
+            "# HELP risemode_frames_sent_total Frames written to displays",
+            "# TYPE risemode_frames_sent_total counter",
+            "risemode_frames_sent_total 120",
+            "risemode_reopens_total 2",
+            "risemode_usb_resets_total 0",
+        ] {
+            assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, text);
+        }
+        assert!(text.ends_with('\n'));
+    }
+    
+    #[test]
+    fn every_sample_follows_the_type_of_its_name() {
+        let text = textfile().render(&Counters::default(), 1);
+        let mut family = None;
+        
+        for line in text.lines() {
+            if let Some(declared) = line.strip_prefix("# TYPE ") {
+                family = declared.split(' ').next();
+            } else if !line.starts_with('#') {
+                let name = line.split(['{', ' ']).next();
+                assert_eq!(name, family, "{:?} doesn't match its TYPE line", line);
+            }
+        }
+    }
+    
+    #[test]
+    fn forgotten_and_disconnected_displays_are_not_reported() {
+        let mut textfile = textfile();
+        textfile.forget("top");
+        let text = textfile.render(&Counters::default(), 1);
+        assert!(!text.contains(r#"display="top""#), "{}", text);
+        assert!(text.contains(r#"display="",metric="temperature""#), "{}", text);
+        
+        textfile.path = std::env::temp_dir().join(format!("risemode-textfile-test-{}.prom", std::process::id()));
+        textfile.write(&Counters::default(), 0);
+        let written = fs::read_to_string(&textfile.path).unwrap();
+        let _ = fs::remove_file(&textfile.path);
+        assert!(!written.contains("display="), "{}", written);
+        assert!(written.contains("risemode_displays_connected 0"), "{}", written);
+    }
+    
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape("front"), "front");
+        assert_eq!(escape(r#"a "b""#), r#"a \"b\""#);
+        assert_eq!(escape(r"C:\temp"), r"C:\\temp");
+        assert_eq!(escape("two\nlines"), r"two\nlines");
+        assert_eq!(labels("x\"", "load"), r#"{display="x\"",metric="load"}"#);
+    }
+}
@@ -0,0 +1,28 @@
+//! The link frames travel over to reach the display.
+//!
+//! [`RisemodeDevice`](crate::RisemodeDevice) talks through [`HidTransport`],
+//! which is `hidapi`'s device for real hardware. The `test-util` feature adds
+//! an in-memory one, `testing::MockTransport`.
+
+use anyhow::Result;
+use hidapi::HidDevice;
+
+/// Something reports can be written to and read from.
+pub trait HidTransport {
+    /// Writes one report and returns how many bytes were sent.
+    fn write(&self, data: &[u8]) -> Result<usize>;
+    
+    /// Waits up to `timeout_ms` (0 = don't wait) for an incoming report and
+    /// returns its length, 0 if none arrived.
+    fn read_timeout(&self, buf: &mut [u8], timeout_ms: i32) -> Result<usize>;
+}
+
+impl HidTransport for HidDevice {
+    fn write(&self, data: &[u8]) -> Result<usize> {
+        Ok(HidDevice::write(self, data)?)
+    }
+    
+    fn read_timeout(&self, buf: &mut [u8], timeout_ms: i32) -> Result<usize> {
+        Ok(HidDevice::read_timeout(self, buf, timeout_ms)?)
+    }
+}
@@ -0,0 +1,217 @@
+//! Local usage counters, persisted in a small state file.
+//!
+//! Nothing here ever leaves the machine; `risemode-driver info` prints the
+//! totals so users can judge long-term reliability.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How often the counters are written back while running.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Totals accumulated over every run of the driver.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Counters {
+    pub runs: u64,
+    pub uptime_secs: u64,
+    pub connects: u64,
+    pub frames_sent: u64,
+    pub sensor_failures: u64,
+    pub reopens: u64,
+    pub reenumerations: u64,
+    pub usb_resets: u64,
+    pub reports_received: u64,
+}
+
+impl Counters {
+    /// Parses the `key = value` lines of the state file, ignoring unknown keys.
+    fn parse(text: &str) -> Self {
+        let mut counters = Self::default();
+        
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse() else {
+                continue;
+            };
+            
+            match key.trim() {
+                "runs" => counters.runs = value,
+                "uptime_secs" => counters.uptime_secs = value,
+                "connects" => counters.connects = value,
+                "frames_sent" => counters.frames_sent = value,
+                "sensor_failures" => counters.sensor_failures = value,
+                "reopens" => counters.reopens = value,
+                "reenumerations" => counters.reenumerations = value,
+                "usb_resets" => counters.usb_resets = value,
+                "reports_received" => counters.reports_received = value,
+                _ => {}
+            }
+        }
+        
+        counters
+    }
+    
+    fn serialize(&self) -> String {
+        format!(
+            "runs = {}\nuptime_secs = {}\nconnects = {}\nframes_sent = {}\nsensor_failures = {}\n\
+             reopens = {}\nreenumerations = {}\nusb_resets = {}\nreports_received = {}\n",
+            self.runs,
+            self.uptime_secs,
+            self.connects,
+            self.frames_sent,
+            self.sensor_failures,
+            self.reopens,
+            self.reenumerations,
+            self.usb_resets,
+            self.reports_received
+        )
+    }
+}
+
+/// Directory for the driver's state: `$STATE_DIRECTORY` when started by
+/// systemd with `StateDirectory=`, otherwise the XDG state directory.
+pub fn state_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("STATE_DIRECTORY") {
+        return PathBuf::from(dir);
+    }
+    
+    if let Some(dir) = env::var_os("XDG_STATE_HOME") {
+        return PathBuf::from(dir).join("risemode-driver");
+    }
+    
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".local/state/risemode-driver"),
+        None => PathBuf::from("/var/lib/risemode-driver"),
+    }
+}
+
+fn counters_path() -> PathBuf {
+    state_dir().join("counters")
+}
+
+/// Reads the persisted counters; a missing file means a fresh install.
+pub fn load() -> Result<Counters> {
+    let path = counters_path();
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(Counters::parse(&text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Counters::default()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Counters for the current run, saved periodically on top of the totals
+/// from previous runs.
+pub struct Usage {
+    pub counters: Counters,
+    last_save: Instant,
+}
+
+impl Usage {
+    pub fn start() -> Self {
+        let mut counters = load().unwrap_or_else(|e| {
+            warn!("Starting usage counters from zero: {:#}", e);
+            Counters::default()
+        });
+        counters.runs += 1;
+        
+        Self { counters, last_save: Instant::now() }
+    }
+    
+    /// Adds the time since the last save to the uptime and writes the file
+    /// once per [`SAVE_INTERVAL`].
+    pub fn maybe_save(&mut self) {
+        if self.last_save.elapsed() < SAVE_INTERVAL {
+            return;
+        }
+        
+        self.save();
+    }
+    
+    pub fn save(&mut self) {
+        self.counters.uptime_secs += self.last_save.elapsed().as_secs();
+        self.last_save = Instant::now();
+        
+        if let Err(e) = write_atomically(&self.counters) {
+            warn!("Could not save usage counters: {:#}", e);
+        }
+    }
+}
+
+fn write_atomically(counters: &Counters) -> Result<()> {
+    let path = counters_path();
+    let dir = state_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    
+    // Write to a temporary file first so a crash never leaves a torn file
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, counters.serialize()).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Prints the totals for `risemode-driver info`.
+pub fn print_info() -> Result<()> {
+    let counters = load()?;
+    let uptime = counters.uptime_secs;
+    
+    println!("State file: {}", counters_path().display());
+    println!("Runs: {}", counters.runs);
+    println!("Uptime: {}d {}h {}m", uptime / 86400, uptime % 86400 / 3600, uptime % 3600 / 60);
+    println!("Device connects: {}", counters.connects);
+    println!("Reconnects: {}", counters.connects.saturating_sub(counters.runs));
+    println!("Frames sent: {}", counters.frames_sent);
+    println!("Sensor read failures: {}", counters.sensor_failures);
+    println!("Reports received from displays: {}", counters.reports_received);
+    println!(
+        "Recoveries: {} reopen(s), {} re-enumeration(s), {} USB reset(s)",
+        counters.reopens, counters.reenumerations, counters.usb_resets
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn counters_survive_a_round_trip() {
+        let counters = Counters {
+            runs: 12,
+            uptime_secs: 86_400,
+            connects: 14,
+            frames_sent: 1_234_567,
+            sensor_failures: 3,
+            reopens: 2,
+            reenumerations: 1,
+            usb_resets: 0,
+            reports_received: u64::MAX,
+        };
+        
+        assert_eq!(Counters::parse(&counters.serialize()), counters);
+    }
+    
+    #[test]
+    fn unknown_and_garbled_lines_are_skipped() {
+        let text = "runs = 4\n\
+                    # comment\n\
+                    future_counter = 9\n\
+                    frames_sent = lots\n\
+                    connects = -1\n\
+                    reopens\n\
+                    \n\
+                      usb_resets=  7  \n";
+        
+        assert_eq!(Counters::parse(text), Counters { runs: 4, usb_resets: 7, ..Counters::default() });
+    }
+    
+    #[test]
+    fn empty_file_is_a_fresh_start() {
+        assert_eq!(Counters::parse(""), Counters::default());
+    }
+}
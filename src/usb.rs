@@ -0,0 +1,96 @@
+//! USB power management of the display, through sysfs.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+/// Resolves the sysfs directory of the USB device a hidraw node belongs to,
+/// e.g. `/dev/hidraw3` -> `/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4`.
+pub fn sysfs_device(hidraw_path: &str) -> Option<PathBuf> {
+    let name = Path::new(hidraw_path).file_name()?;
+    let mut dir = fs::canonicalize(Path::new("/sys/class/hidraw").join(name).join("device")).ok()?;
+    
+    // Walk up from the HID interface until we reach the USB device itself
+    loop {
+        if dir.join("idVendor").exists() {
+            return Some(dir);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// Writes `on` to the device's `power/control` attribute so the kernel never
+/// suspends the idle HID interface. Returns whether the setting was changed.
+pub fn disable_autosuspend(hidraw_path: &str) -> Result<bool> {
+    let usb_dir = sysfs_device(hidraw_path).context("Could not locate the USB device in sysfs")?;
+    
+    if read_sysfs_attr(&usb_dir, "power/control") == "on" {
+        return Ok(false);
+    }
+    
+    let control = usb_dir.join("power/control");
+    fs::write(&control, "on").with_context(|| format!("Failed to write {}", control.display()))?;
+    Ok(true)
+}
+
+/// Logically disconnects the USB device and connects it again by toggling its
+/// `authorized` attribute, the same as replugging the cable as far as the
+/// display controller is concerned. `usb_dir` is a directory returned by
+/// [`sysfs_device`]. Needs root.
+pub fn power_cycle(usb_dir: &Path) -> Result<()> {
+    let authorized = usb_dir.join("authorized");
+    
+    fs::write(&authorized, "0").with_context(|| format!("Failed to write {}", authorized.display()))?;
+    // Give the controller time to notice it lost the host
+    thread::sleep(Duration::from_millis(500));
+    fs::write(&authorized, "1").with_context(|| format!("Failed to write {}", authorized.display()))?;
+    
+    Ok(())
+}
+
+fn read_sysfs_attr(dir: &Path, attr: &str) -> String {
+    fs::read_to_string(dir.join(attr))
+        .map(|value| value.trim().to_string())
+        .unwrap_or_else(|_| "unavailable".to_string())
+}
+
+/// Logs the USB power management state around a device that keeps failing
+/// writes. Autosuspend on the HID interface is a common cause of a frozen display.
+pub fn print_diagnostics(hidraw_path: &str) {
+    warn!("Repeated write failures on {}, checking USB power management...", hidraw_path);
+    
+    let Some(usb_dir) = sysfs_device(hidraw_path) else {
+        warn!("Could not locate the USB device in sysfs");
+        return;
+    };
+    
+    warn!("USB device: {}", usb_dir.display());
+    let control = read_sysfs_attr(&usb_dir, "power/control");
+    warn!("Autosuspend control: {}", control);
+    warn!("Autosuspend delay: {} ms", read_sysfs_attr(&usb_dir, "power/autosuspend_delay_ms"));
+    warn!("Runtime status: {}", read_sysfs_attr(&usb_dir, "power/runtime_status"));
+    
+    // The port the device is plugged into, exposed by the parent hub
+    let port = usb_dir.join("port");
+    if port.exists() {
+        warn!("Port power control: {}", read_sysfs_attr(&port, "power/control"));
+        warn!("Port over-current events: {}", read_sysfs_attr(&port, "over_current_count"));
+    }
+    
+    if let Some(hub) = usb_dir.parent().filter(|dir| dir.join("idVendor").exists()) {
+        warn!("Parent hub: {} ({})", hub.display(), read_sysfs_attr(hub, "product"));
+        warn!("Parent hub autosuspend control: {}", read_sysfs_attr(hub, "power/control"));
+    }
+    
+    if control == "auto" {
+        warn!(
+            "USB autosuspend is enabled for the display. To disable it, run: echo on | sudo tee {}",
+            usb_dir.join("power/control").display()
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Frames the driver writes for given readings, checked against an in-memory
+//! transport instead of the real display.
+
+use risemode_driver::protocol::{Frame, FrameError, FRAME_LEN};
+use risemode_driver::testing::{self, MockTransport, TestDevice};
+use risemode_driver::RisemodeDevice;
+use std::time::Duration;
+
+fn mock_device() -> (RisemodeDevice, MockTransport) {
+    let transport = MockTransport::new();
+    (transport.device(), transport)
+}
+
+fn frame(integer: u8, decimal: u8) -> Vec<u8> {
+    let mut frame = vec![0; FRAME_LEN];
+    frame[0] = integer;
+    frame[1] = decimal;
+    frame
+}
+
+#[test]
+fn temperatures_encode_to_integer_and_tenths() {
+    let cases = [
+        (42.5, frame(42, 5)),
+        (45.3, frame(45, 3)),
+        (0.0, frame(0, 0)),
+        (99.9, frame(99, 9)),
+        (37.96, frame(38, 0)),
+        (100.0, frame(100, 0)),
+    ];
+    
+    for (temp, expected) in cases {
+        let (device, transport) = mock_device();
+        device.send(&Frame::temperature(temp).unwrap()).unwrap();
+        assert_eq!(transport.reports(), vec![expected], "for {}°C", temp);
+    }
+}
+
+#[test]
+fn out_of_range_values_are_not_sent() {
+    for temp in [-5.0, 256.0, 300.0, f32::NAN] {
+        assert!(matches!(Frame::temperature(temp), Err(FrameError::TemperatureOutOfRange(_))), "for {}", temp);
+    }
+    
+    let (device, transport) = mock_device();
+    device.send(&Frame::temperature(0.0).unwrap()).unwrap();
+    device.send(&Frame::temperature(255.9).unwrap()).unwrap();
+    assert_eq!(transport.reports(), vec![frame(0, 0), frame(255, 9)]);
+}
+
+#[test]
+fn frames_are_written_in_order_and_whole() {
+    let (device, transport) = mock_device();
+    device.send(&Frame::temperature(50.0).unwrap()).unwrap();
+    device.send(&Frame::new()).unwrap();
+    device.send(&Frame::temperature(51.2).unwrap()).unwrap();
+    
+    let reports = transport.reports();
+    assert_eq!(reports, vec![frame(50, 0), vec![0; FRAME_LEN], frame(51, 2)]);
+    assert!(reports.iter().all(|report| report.len() == FRAME_LEN));
+}
+
+#[test]
+fn write_errors_name_the_device() {
+    let (mut device, transport) = mock_device();
+    device.set_name("front");
+    transport.set_failing(true);
+    
+    let error = device.send(&Frame::temperature(40.0).unwrap()).unwrap_err();
+    assert!(format!("{:#}", error).contains("front"), "{:#}", error);
+    assert!(transport.reports().is_empty());
+}
+
+#[test]
+fn incoming_reports_are_read_in_order() {
+    let (device, transport) = mock_device();
+    assert_eq!(device.read_report(Duration::ZERO).unwrap(), None);
+    
+    transport.push_incoming(&[0x01, 0x1e, 0x05]);
+    transport.push_incoming(&[0x02]);
+    
+    let first = device.read_report(Duration::ZERO).unwrap().unwrap();
+    assert_eq!(first.bytes(), [0x01, 0x1e, 0x05]);
+    assert_eq!(first.to_string(), "01 1e 05");
+    assert_eq!(device.read_report(Duration::ZERO).unwrap().unwrap().bytes(), [0x02]);
+    assert_eq!(device.read_report(Duration::ZERO).unwrap(), None);
+}
+
+#[test]
+fn temperature_sweep_matches_golden_file() {
+    let (device, transport) = mock_device();
+    for temp in [0.0, 21.4, 45.3, 67.05, 99.9, 120.0] {
+        device.send(&Frame::temperature(temp).unwrap()).unwrap();
+    }
+    device.send(&Frame::new()).unwrap();
+    
+    testing::assert_golden(&transport, concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/temperature_sweep.txt"));
+}